@@ -0,0 +1,97 @@
+use std::{collections::HashSet, env, fs, path::{Path, PathBuf}, process};
+use carrier_clone::level_description::{file_name, level_files, load_level_file, Level, LEVELS_FOLDER};
+use carrier_clone::xsb::to_xsb;
+
+const USAGE: &str = "usage: export-levels <xsb|ron> <output folder> [level files...]";
+
+//Writes levels out as .xsb boards or as .ron levels with their csv layers,
+//`cargo run --bin export-levels -- xsb exported` exports every level the game plays
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || (args[0] != "xsb" && args[0] != "ron") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let output = PathBuf::from(&args[1]);
+
+    let paths = if args.len() > 2 {
+        args[2..].iter().map(PathBuf::from).collect()
+    } else {
+        level_files(LEVELS_FOLDER).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(2);
+        })
+    };
+
+    let mut exported = 0;
+    let mut failed = 0;
+    let mut taken = HashSet::new();
+    for path in paths.iter() {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let levels = match load_level_file(path) {
+            Ok(levels) => levels,
+            Err(error) => {
+                println!("FAIL {}\n     can't be loaded: {}", file, error);
+                failed += 1;
+                continue;
+            },
+        };
+
+        for level in levels.iter() {
+            let name = unique_name(&level.name, &mut taken);
+            let result = if args[0] == "xsb" { write_xsb(level, &name, &output) } else { write_ron(level, &name, &output) };
+            match result {
+                Ok(written) => {
+                    println!("ok   {} \"{}\": {}", file, level.name, written.display());
+                    exported += 1;
+                },
+                Err(error) => {
+                    println!("FAIL {} \"{}\"\n     {}", file, level.name, error);
+                    failed += 1;
+                },
+            }
+        }
+    }
+
+    println!("{} levels exported, {} failed", exported, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+//The file name of the level, numbered when an earlier level of the export already has it,
+//levels of the same name in two files would overwrite each other otherwise
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let name = file_name(name);
+    let unique = (1..)
+        .map(|number| if number == 1 { name.clone() } else { format!("{}_{}", name, number) })
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap();
+    taken.insert(unique.clone());
+    unique
+}
+
+fn write_xsb(level: &Level, name: &str, output: &Path) -> Result<PathBuf, String> {
+    let text = to_xsb(level)?;
+    let path = output.join(format!("{}.xsb", name));
+    write(&path, &text)?;
+    Ok(path)
+}
+
+//Laid out like the assets folder: the level in levels and its layers in maps
+fn write_ron(level: &Level, name: &str, output: &Path) -> Result<PathBuf, String> {
+    let (description, layers) = level.to_description(name);
+    for (layer, csv) in layers.iter() {
+        write(&output.join(layer), csv)?;
+    }
+    let path = output.join("levels").join(format!("{}.ron", name));
+    write(&path, &description.to_ron()?)?;
+    Ok(path)
+}
+
+fn write(path: &Path, text: &str) -> Result<(), String> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(|e| format!("{}: {}", folder.display(), e))?;
+    }
+    fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,70 @@
+use std::{process, time::Instant};
+use carrier_clone::level_description::{level_files, load_level_file, Level, LEVELS_FOLDER};
+use carrier_clone::puzzle::PuzzleState;
+use carrier_clone::solver::{solve, SEARCH_LIMIT};
+use carrier_clone::validation::check_level;
+
+//Loads every level the game would play and checks it can be finished, `cargo run --bin validate-levels`
+fn main() {
+    let paths = match level_files(LEVELS_FOLDER) {
+        Ok(paths) => paths,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        },
+    };
+
+    let mut checked = 0;
+    let mut failed = 0;
+    for path in paths.iter() {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let levels = match load_level_file(path) {
+            Ok(levels) => levels,
+            Err(error) => {
+                println!("FAIL {}\n     can't be loaded: {}", file, error);
+                checked += 1;
+                failed += 1;
+                continue;
+            },
+        };
+
+        //A .xsb collection has many levels in one file
+        for level in levels.iter() {
+            checked += 1;
+            if !check(&file, level) {
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} levels checked, {} failed", checked, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+//Prints the result of one level, false when it failed
+fn check(file: &str, level: &Level) -> bool {
+    let problems = check_level(level);
+    if !problems.is_empty() {
+        println!("FAIL {} \"{}\"", file, level.name);
+        for problem in problems {
+            println!("     {}", problem);
+        }
+        return false;
+    }
+
+    let start = Instant::now();
+    match solve(&PuzzleState::from_level(level), SEARCH_LIMIT) {
+        Some(solution) => {
+            println!("ok   {} \"{}\": solved in {} pushes and {} steps ({:.2}s)",
+                file, level.name, solution.pushes, solution.steps.len(), start.elapsed().as_secs_f32());
+            true
+        },
+        None => {
+            println!("FAIL {} \"{}\"\n     the solver found no solution within {} positions",
+                file, level.name, SEARCH_LIMIT);
+            false
+        },
+    }
+}
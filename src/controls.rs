@@ -0,0 +1,406 @@
+use bevy::prelude::*;
+use super::{despawn_screen, GameState, ui::*};
+use super::input::{Action, KeyBindings, ACTIONS, FIRST_EDITOR_ACTION};
+use super::deadlock_warning::DeadlockSettings;
+
+pub struct ControlsPlugin;
+
+//Helps to despawn entities
+#[derive(Component)]
+struct OnControlsScreen;
+
+//One of the two keys of an action, the rows after the actions are the deadlock warnings toggle
+//and the defaults and back buttons
+#[derive(Component, Clone, Copy, Eq, PartialEq)]
+pub struct BindingSlot{
+    row: usize,
+    slot: usize,
+}
+
+const WARNINGS_ROW: usize = ACTIONS.len();
+const DEFAULTS_ROW: usize = ACTIONS.len() + 1;
+const BACK_ROW: usize = ACTIONS.len() + 2;
+
+//The current selected slot
+pub struct SelectedSlot(BindingSlot);
+
+//The buttons shown while waiting for a key. Every key can be bound, so these are the way out without one.
+#[derive(Component, Clone, Copy)]
+pub enum ListenChoice {
+    Clear,
+    Keep,
+}
+
+//The row the listen choices go in, it keeps its place while they are hidden
+#[derive(Component)]
+struct ChoiceRow;
+
+//Waiting for the key that goes into the selected slot
+pub struct Listening(bool);
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App){
+        app
+        // When entering the state, spawn everything needed for this screen
+        .add_system_set(SystemSet::on_enter(GameState::Controls).with_system(setup))
+
+        // Run those systems on update for each frame
+        .add_system_set(SystemSet::on_update(GameState::Controls)
+            .with_system(listen_for_key)
+            .with_system(mark_selected)
+            .with_system(next_previous_button.after(listen_for_key))
+            .with_system(select_button.after(listen_for_key))
+            .with_system(mouse_on_button)
+            .with_system(show_bindings)
+            .with_system(show_listen_choices.after(listen_for_key))
+            )
+
+        // When exiting the state, keep the bindings and settings and despawn everything that was spawned for this screen
+        .add_system_set(SystemSet::on_exit(GameState::Controls)
+            .with_system(save_bindings)
+            .with_system(save_deadlock_settings)
+            .with_system(despawn_screen::<OnControlsScreen>),);
+    }
+}
+
+// spawn everything needed for this screen
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut actions: ResMut<Input<Action>>){
+    //The confirm that opened this screen mustn't start listening right away
+    actions.clear();
+    commands.spawn_bundle(Camera2dBundle::default()).insert(OnControlsScreen);
+
+    let font = asset_server.load("fonts/retganon.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 26.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    let table = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(OnControlsScreen)
+        .id();
+
+    //The game's actions on the left and the editor's on the right, the rows still go on from one to the other
+    let columns = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                //The ui goes up from the bottom, this lines up the tops of the columns
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .id();
+    commands.entity(table).add_child(columns);
+    for (title, rows) in [("GAME", 0..FIRST_EDITOR_ACTION), ("EDITOR", FIRST_EDITOR_ACTION..ACTIONS.len())] {
+        let column = commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::ColumnReverse,
+                    align_items: AlignItems::Center,
+                    margin: UiRect{ left: Val::Px(24.), right: Val::Px(24.), ..default() },
+                    ..default()
+                },
+                color: UiColor(Color::NONE),
+                ..default()
+            })
+            .id();
+        let heading = commands.spawn_bundle(TextBundle::from_section(title, text_style.clone())).id();
+        commands.entity(column).add_child(heading);
+        for row in rows {
+            let line = commands
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..default()
+                })
+                .id();
+            let label = commands.spawn_bundle(TextBundle::from_section(ACTIONS[row].1, text_style.clone())
+                .with_style(Style {
+                    size: Size::new(Val::Px(260.0), Val::Auto),
+                    ..default()
+                })).id();
+            commands.entity(line).add_child(label);
+            for slot in 0..2 {
+                let button = spawn_slot(&mut commands, &text_style, BindingSlot{ row, slot }, "", Size::new(Val::Px(192.0), Val::Px(36.0)));
+                commands.entity(line).add_child(button);
+            }
+            commands.entity(column).add_child(line);
+        }
+        commands.entity(columns).add_child(column);
+    }
+
+    let line = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .id();
+    let label = commands.spawn_bundle(TextBundle::from_section("Deadlock warnings", text_style.clone())
+        .with_style(Style {
+            size: Size::new(Val::Px(260.0), Val::Auto),
+            ..default()
+        })).id();
+    let toggle = spawn_slot(&mut commands, &text_style, BindingSlot{ row: WARNINGS_ROW, slot: 0 }, "", Size::new(Val::Px(192.0), Val::Px(36.0)));
+    commands.entity(line).push_children(&[label, toggle]);
+    commands.entity(table).add_child(line);
+
+    let defaults = spawn_slot(&mut commands, &text_style, BindingSlot{ row: DEFAULTS_ROW, slot: 0 }, "", Size::new(Val::Px(260.0), Val::Px(48.0)));
+    let back = spawn_slot(&mut commands, &text_style, BindingSlot{ row: BACK_ROW, slot: 0 }, "", Size::new(Val::Px(260.0), Val::Px(48.0)));
+    commands.entity(table).push_children(&[defaults, back]);
+
+    let choices = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Auto, Val::Px(48.0)),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(ChoiceRow)
+        .id();
+    commands.entity(table).add_child(choices);
+
+    let help = commands.spawn_bundle(TextBundle::from_section(
+        "Confirm on a key to change it, then press the new key. Any key can be bound, Clear and Keep are on screen",
+        TextStyle { font_size: 20.0, ..text_style.clone() },
+    ).with_style(Style {
+        margin: UiRect::all(Val::Px(8.)),
+        ..default()
+    })).id();
+    commands.entity(table).add_child(help);
+
+    commands.insert_resource(SelectedSlot(BindingSlot{ row: 0, slot: 0 }));
+    commands.insert_resource(Listening(false));
+}
+
+//The text of the binding slots is filled in by show_bindings, the listen choices have theirs from the start
+fn spawn_slot(commands: &mut Commands, text_style: &TextStyle, slot: impl Component, text: &str, size: Size<Val>) -> Entity{
+    commands.spawn_bundle(ButtonBundle {
+        style: Style {
+            size,
+            margin: UiRect::all(Val::Px(4.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::rgb(0.53, 0.38, 0.2).into(),
+        ..default()
+    })
+    .insert(slot)
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle::from_section(text, text_style.clone()));
+    }).id()
+}
+
+fn show_bindings(
+    bindings: Res<KeyBindings>,
+    listening: Res<Listening>,
+    selected: Res<SelectedSlot>,
+    settings: Res<DeadlockSettings>,
+    slots: Query<(&BindingSlot, &Children)>,
+    mut texts: Query<&mut Text>,
+){
+    if !bindings.is_changed() && !listening.is_changed() && !settings.is_changed() { return; }
+    for (slot, children) in slots.iter() {
+        let value = match slot.row {
+            WARNINGS_ROW => if settings.warnings { "ON" } else { "OFF" }.to_string(),
+            DEFAULTS_ROW => "DEFAULTS".to_string(),
+            BACK_ROW => "BACK".to_string(),
+            _ if listening.0 && *slot == selected.0 => "press a key".to_string(),
+            row => match bindings.keys(ACTIONS[row].0)[slot.slot] {
+                Some(key) => format!("{:?}", key),
+                None => "-".to_string(),
+            },
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
+
+//The next key pressed goes into the selected slot, swapped with the slot that had it before.
+//No key is kept back for leaving, the clear and keep buttons or a gamepad's back button do that.
+fn listen_for_key(
+    mut listening: ResMut<Listening>,
+    selected: Res<SelectedSlot>,
+    keys: Res<Input<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut actions: ResMut<Input<Action>>,
+    choices: Query<(&Interaction, &ListenChoice), (Changed<Interaction>, With<Button>)>,
+){
+    if !listening.0 { return; }
+    let clicked = choices.iter().find(|(interaction, _)| **interaction == Interaction::Clicked).map(|(_, choice)| *choice);
+    let key = match (keys.get_just_pressed().next(), clicked) {
+        (Some(key), _) => Some(*key),
+        (None, Some(ListenChoice::Clear)) => None,
+        (None, Some(ListenChoice::Keep)) => {
+            listening.0 = false;
+            return;
+        },
+        //A key press was bound above, so back can only come from a gamepad here
+        (None, None) if actions.just_pressed(Action::Back) => {
+            listening.0 = false;
+            //Going back mustn't also leave the screen
+            actions.clear();
+            return;
+        },
+        (None, None) => return,
+    };
+    bindings.bind(ACTIONS[selected.0.row].0, selected.0.slot, key);
+    listening.0 = false;
+    //The key just bound mustn't also work as the action it had before
+    actions.clear();
+}
+
+//The clear and keep buttons are there only while a key is awaited
+fn show_listen_choices(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    listening: Res<Listening>,
+    rows: Query<Entity, With<ChoiceRow>>,
+    choices: Query<Entity, With<ListenChoice>>,
+){
+    if !listening.is_changed() { return; }
+    for entity in choices.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !listening.0 { return; }
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/retganon.ttf"),
+        font_size: 26.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    for row in rows.iter() {
+        let clear = spawn_slot(&mut commands, &text_style, ListenChoice::Clear, "CLEAR", Size::new(Val::Px(192.0), Val::Px(36.0)));
+        let keep = spawn_slot(&mut commands, &text_style, ListenChoice::Keep, "KEEP", Size::new(Val::Px(192.0), Val::Px(36.0)));
+        commands.entity(row).push_children(&[clear, keep]);
+    }
+}
+
+//Change the selected slot scale so you know with is it
+fn mark_selected(selected: Res<SelectedSlot>, mut query: Query<(&mut Transform, &BindingSlot)>){
+    for (mut transform, slot) in query.iter_mut(){
+        if selected.0 == *slot{
+            transform.scale = Vec3::new(1.1, 1.1, 1.);
+        }
+        else{
+            transform.scale = Vec3::new(1., 1., 1.);
+        }
+    }
+}
+
+//Up and down go through the rows, left and right between the two keys of an action
+fn next_previous_button(mut selected: ResMut<SelectedSlot>, actions: Res<Input<Action>>, listening: Res<Listening>){
+    if listening.0 { return; }
+    let rows = BACK_ROW + 1;
+    let mut slot = selected.0;
+
+    if actions.just_pressed(Action::MoveDown) {
+        slot.row = (slot.row + 1) % rows;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        slot.row = (slot.row + rows - 1) % rows;
+    }
+    if actions.just_pressed(Action::MoveLeft) || actions.just_pressed(Action::MoveRight) {
+        slot.slot = 1 - slot.slot;
+    }
+    //The buttons under the actions have a single slot
+    if slot.row >= WARNINGS_ROW {
+        slot.slot = 0;
+    }
+    selected.0 = slot;
+}
+
+fn select_button(
+    selected: Res<SelectedSlot>,
+    actions: Res<Input<Action>>,
+    mut listening: ResMut<Listening>,
+    mut bindings: ResMut<KeyBindings>,
+    mut settings: ResMut<DeadlockSettings>,
+    mut game_state: ResMut<State<GameState>>,
+){
+    if listening.0 { return; }
+    if actions.just_pressed(Action::Confirm) {
+        accept_selection(selected.0, &mut listening, &mut bindings, &mut settings, &mut game_state);
+    }
+    else if actions.just_pressed(Action::Back) {
+        game_state.replace(GameState::Menu).unwrap();
+    }
+}
+
+fn mouse_on_button(
+    interaction_query: Query<
+        (&Interaction, &BindingSlot),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut selected: ResMut<SelectedSlot>,
+    mut listening: ResMut<Listening>,
+    mut bindings: ResMut<KeyBindings>,
+    mut settings: ResMut<DeadlockSettings>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if listening.0 { return; }
+    for (interaction, slot) in interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => {
+                selected.0 = *slot;
+                accept_selection(*slot, &mut listening, &mut bindings, &mut settings, &mut game_state);
+            },
+            Interaction::Hovered => {
+                selected.0 = *slot;
+            },
+            Interaction::None => {},
+        }
+    }
+}
+
+fn accept_selection(
+    slot: BindingSlot,
+    listening: &mut ResMut<Listening>,
+    bindings: &mut ResMut<KeyBindings>,
+    settings: &mut ResMut<DeadlockSettings>,
+    game_state: &mut ResMut<State<GameState>>,
+){
+    match slot.row {
+        WARNINGS_ROW => settings.warnings = !settings.warnings,
+        DEFAULTS_ROW => **bindings = KeyBindings::default(),
+        BACK_ROW => game_state.replace(GameState::Menu).unwrap(),
+        _ => listening.0 = true,
+    }
+}
+
+fn save_bindings(bindings: Res<KeyBindings>){
+    bindings.write();
+}
+
+fn save_deadlock_settings(settings: Res<DeadlockSettings>){
+    settings.write();
+}
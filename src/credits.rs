@@ -0,0 +1,186 @@
+use std::fs;
+use bevy::prelude::*;
+use super::{despawn_screen, GameState, ui::*};
+use super::levels::LevelList;
+use super::save_game::SaveGame;
+use super::splash::{fade_alpha, FadeEffectTimer};
+use super::input::Action;
+
+//Every line is one line of the credits, lines starting with "# " are headings
+const CREDITS_FILE: &str = "assets/credits.txt";
+//Pixels per second the credits move up
+const SCROLL_SPEED: f32 = 60.0;
+
+pub struct CreditsPlugin;
+
+//Helps to despawn entities
+#[derive(Component)]
+struct OnCreditsScreen;
+
+//The column with all the credit lines, it starts under the screen and moves up
+#[derive(Component)]
+struct CreditsScroll;
+
+//The totals and the menu button, shown once the credits are gone
+#[derive(Component)]
+struct CreditsSummary;
+
+impl Plugin for CreditsPlugin{
+    fn build(&self, app: &mut App){
+        app
+        // When entering the state, spawn everything needed for this screen
+        .add_system_set(SystemSet::on_enter(GameState::Credits).with_system(setup))
+        // Run those systems on update for each frame
+        .add_system_set(SystemSet::on_update(GameState::Credits)
+            .with_system(scroll_credits)
+            .with_system(show_summary.after(scroll_credits))
+            .with_system(back_to_menu)
+            .with_system(mouse_on_button)
+            )
+        // When exiting the state, despawn everything that was spawned for this screen
+        .add_system_set(SystemSet::on_exit(GameState::Credits)
+            .with_system(despawn_screen::<OnCreditsScreen>),);
+    }
+}
+
+// spawn everything needed for this screen
+fn setup(mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveGame>,
+    levels: Res<LevelList>,
+    mut actions: ResMut<Input<Action>>,
+){
+    //The confirm that finished the last level mustn't skip the credits
+    actions.clear();
+    commands.spawn_bundle(Camera2dBundle::default()).insert(OnCreditsScreen);
+
+    let font = asset_server.load("fonts/retganon.ttf");
+    let credits = fs::read_to_string(CREDITS_FILE).unwrap_or_else(|error| {
+        warn!("Couldn't read the credits from {}: {}", CREDITS_FILE, error);
+        String::new()
+    });
+
+    let scroll = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect{ top: Val::Px(1080.), left: Val::Px(0.), ..default() },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(CreditsScroll)
+        .insert(OnCreditsScreen)
+        .id();
+
+    for line in credits.lines() {
+        let (text, size, color) = match line.strip_prefix("# ") {
+            Some(heading) => (heading, 48.0, Color::rgb(0.53, 0.38, 0.2)),
+            None => (line, 30.0, Color::rgb(0.9, 0.9, 0.9)),
+        };
+        //Empty lines still take the height of a line
+        let text = if text.is_empty() { " " } else { text };
+        let line = commands.spawn_bundle(TextBundle::from_section(
+            text,
+            TextStyle { font: font.clone(), font_size: size, color },
+        ).with_style(Style {
+            margin: UiRect::all(Val::Px(6.)),
+            ..default()
+        })).id();
+        commands.entity(scroll).add_child(line);
+    }
+
+    //The imported collections aren't part of the game the credits are for
+    let own_levels: Vec<_> = levels.0.iter().filter(|level| level.collection.is_none()).collect();
+    let completed = own_levels.iter()
+        .filter(|level| save.levels.get(&level.id()).is_some_and(|progress| progress.completed))
+        .count();
+    let seconds = save.total_seconds as u32;
+    let totals = [
+        format!("Levels completed {} of {}", completed, own_levels.len()),
+        format!("Total moves {}   Total pushes {}", save.total_moves, save.total_pushes),
+        format!("Play time {}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    ];
+
+    let summary = spawn_menu_container(&mut commands);
+    commands.entity(summary)
+        .insert(Visibility{ is_visible: false })
+        .insert(CreditsSummary)
+        .insert(OnCreditsScreen)
+        //Fades in over the first half, then it is left at full color
+        .insert(FadeEffectTimer(Timer::from_seconds(3.0, false)));
+    for line in totals {
+        let text = spawn_text(&mut commands, &asset_server, &line, OnCreditsScreen);
+        commands.entity(summary).add_child(text);
+    }
+    let button = spawn_button(&mut commands, &asset_server, "MENU", OnCreditsScreen);
+    commands.entity(summary).add_child(button);
+}
+
+fn scroll_credits(mut query: Query<(&mut Style, &Node, &mut Visibility), With<CreditsScroll>>, time: Res<Time>){
+    for (mut style, node, mut visibility) in query.iter_mut() {
+        if let Val::Px(top) = style.position.top {
+            let top = top - SCROLL_SPEED * time.delta_seconds();
+            style.position.top = Val::Px(top);
+            if top < -node.size.y {
+                visibility.is_visible = false;
+            }
+        }
+    }
+}
+
+//The totals come once the last credit line left the screen
+fn show_summary(
+    credits: Query<&Visibility, (With<CreditsScroll>, Without<CreditsSummary>)>,
+    mut summary: Query<(&mut Visibility, &mut FadeEffectTimer, &Children), With<CreditsSummary>>,
+    mut texts: Query<&mut Text>,
+    time: Res<Time>,
+){
+    if credits.iter().any(|visibility| visibility.is_visible) { return; }
+
+    for (mut visibility, mut timer, children) in summary.iter_mut() {
+        visibility.is_visible = true;
+        if timer.finished() { continue; }
+        timer.tick(time.delta());
+        let alpha = if timer.percent() < 0.5 { fade_alpha(&timer) } else { 1.0 };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+//Confirm or back go to the menu at any time
+fn back_to_menu(mut actions: ResMut<Input<Action>>, mut game_state: ResMut<State<GameState>>){
+    if actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Back) {
+        game_state.replace(GameState::Menu).unwrap();
+        actions.clear();
+    }
+}
+
+fn mouse_on_button(
+    mut interaction_query: Query<
+        (&Interaction, &mut Transform),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    for (interaction, mut button_transform) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                game_state.replace(GameState::Menu).unwrap();
+            },
+            Interaction::Hovered => {
+                button_transform.scale = Vec3::new(1.2, 1.2, 1.);
+            },
+            Interaction::None => {
+                button_transform.scale = Vec3::new(1., 1., 1.);
+            },
+        }
+    }
+}
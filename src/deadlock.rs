@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use super::game_grid::{GridCell, GridCellType};
+use super::puzzle::{Facing, PuzzleState, BROKEN};
+
+pub const DIRECTIONS: [Facing; 4] = [Facing::Up, Facing::Down, Facing::Right, Facing::Left];
+
+//The cells of one y level of the grid
+pub fn layer_cells(puzzle: &PuzzleState, y: i32) -> Vec<GridCell> {
+    let mut cells: Vec<GridCell> = puzzle.grid.grid.keys()
+        .filter(|cell| cell.y == y)
+        .copied()
+        .collect();
+    cells.sort_by_key(|cell| (cell.z, cell.x));
+    cells
+}
+
+//The height every goal is on, none when they are on more than one
+pub fn goal_height(puzzle: &PuzzleState) -> Option<i32> {
+    let y = puzzle.goals.first()?.cell.y;
+    puzzle.goals.iter().all(|goal| goal.cell.y == y).then_some(y)
+}
+
+//The least number of pushes that brings a block from each cell to a goal, ignoring the other blocks
+pub fn goal_distances(puzzle: &PuzzleState) -> HashMap<GridCell, usize> {
+    pull_distances(puzzle, &puzzle.goal_cells())
+}
+
+//The same for every goal on its own, in the order of `goals`
+pub fn distances_to_each_goal(puzzle: &PuzzleState) -> Vec<HashMap<GridCell, usize>> {
+    puzzle.goals.iter().map(|goal| pull_distances(puzzle, &[goal.cell])).collect()
+}
+
+//Worked out backwards from the targets: a block can come from a cell when both that cell and the one
+//behind it, where the player stands to push, are floor or holes that can be filled.
+fn pull_distances(puzzle: &PuzzleState, targets: &[GridCell]) -> HashMap<GridCell, usize> {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    for target in targets.iter() {
+        if puzzle.is_floor(*target) && !distances.contains_key(target) {
+            distances.insert(*target, 0);
+            queue.push_back(*target);
+        }
+    }
+
+    while let Some(cell) = queue.pop_front() {
+        let distance = distances[&cell];
+        for facing in DIRECTIONS {
+            let offset = facing.offset();
+            let from = cell - offset;
+            let player = from - offset;
+            if distances.contains_key(&from) { continue; }
+            if can_hold_block(puzzle, from) && can_hold_block(puzzle, player) {
+                distances.insert(from, distance + 1);
+                queue.push_back(from);
+            }
+        }
+    }
+    distances
+}
+
+//Floor, or a hole a block could fill to make floor. Counting the holes as floor leaves out
+//a few dead squares but never calls a good one dead.
+fn can_hold_block(puzzle: &PuzzleState, cell: GridCell) -> bool {
+    puzzle.is_floor(cell) || (puzzle.is_empty(cell) && puzzle.is_empty(cell + GridCell::new(0, -1, 0)))
+}
+
+//Floor cells of the goals' height a block can never be pushed out of onto a goal, corners that aren't goals
+//for example. Pulling and conveyors get a block out of anywhere, so levels with them have none. Neither have
+//levels with goals on more than one height, a block pushed off a ledge gets to goals no push on its own height reaches.
+pub fn dead_squares(puzzle: &PuzzleState) -> HashSet<GridCell> {
+    if puzzle.rules.pull || !puzzle.conveyors.is_empty() { return HashSet::new(); }
+    let height = match goal_height(puzzle) {
+        Some(height) => height,
+        None => return HashSet::new(),
+    };
+    let distances = goal_distances(puzzle);
+    layer_cells(puzzle, height).into_iter()
+        .filter(|cell| puzzle.is_floor(*cell) && !distances.contains_key(cell))
+        .collect()
+}
+
+//Walls and the outside of the grid, a block can never be pushed into them
+fn is_wall(puzzle: &PuzzleState, cell: GridCell) -> bool {
+    matches!(puzzle.cell(cell), None | Some(Some(GridCellType::Tile(_))))
+}
+
+//The blocks of the 2x2 square starting at the corner when all four cells are blocks or walls
+//and one of the blocks isn't on a goal that takes it, none of them can be pushed again.
+//With chain pushes a block can push its neighbour out of the square, a pulled block comes out
+//of it and so does one on a conveyor, so nothing is frozen that way.
+fn frozen_square(puzzle: &PuzzleState, corner: GridCell) -> Option<Vec<i32>> {
+    if puzzle.rules.chain > 1 || puzzle.rules.pull || !puzzle.conveyors.is_empty() { return None; }
+    let square = [
+        corner,
+        corner + GridCell::new(1, 0, 0),
+        corner + GridCell::new(0, 0, 1),
+        corner + GridCell::new(1, 0, 1),
+    ];
+    let mut blocks = Vec::new();
+    let mut off_goal = false;
+    for cell in square {
+        match puzzle.cell(cell) {
+            Some(Some(GridCellType::MovingTile(id))) => {
+                blocks.push(id);
+                off_goal |= !puzzle.goals.iter().any(|goal| goal.cell == cell && puzzle.is_goal_filled(goal));
+            },
+            _ if is_wall(puzzle, cell) => {},
+            _ => return None,
+        }
+    }
+    if off_goal { Some(blocks) } else { None }
+}
+
+//Blocks stuck in a 2x2 square of blocks and walls, on any height
+pub fn frozen_blocks(puzzle: &PuzzleState) -> Vec<i32> {
+    let mut frozen = HashSet::new();
+    for corner in puzzle.grid.grid.keys().copied() {
+        if let Some(blocks) = frozen_square(puzzle, corner) {
+            frozen.extend(blocks);
+        }
+    }
+    let mut frozen: Vec<i32> = frozen.into_iter().collect();
+    frozen.sort();
+    frozen
+}
+
+//Whether the block on this cell is part of a frozen 2x2 square
+pub fn is_frozen_around(puzzle: &PuzzleState, cell: GridCell) -> bool {
+    [
+        cell,
+        cell - GridCell::new(1, 0, 0),
+        cell - GridCell::new(0, 0, 1),
+        cell - GridCell::new(1, 0, 1),
+    ].into_iter().any(|corner| frozen_square(puzzle, corner).is_some())
+}
+
+//Blocks only ever go down, one that broke or sank below every goal, into a hole for example, is no use any more
+pub fn is_lost(puzzle: &PuzzleState, cell: GridCell) -> bool {
+    cell == BROKEN || puzzle.goals.iter().all(|goal| goal.cell.y > cell.y)
+}
+
+//The blocks that can't reach a goal any more, on a dead square or frozen off a goal, once they and the
+//lost blocks are more than the level can spare. None while the puzzle still looks solvable.
+//A deadlock can have no stuck block to show, when too many fragile blocks broke.
+pub fn deadlocked_blocks(puzzle: &PuzzleState, dead: &HashSet<GridCell>) -> Option<Vec<i32>> {
+    let frozen = frozen_blocks(puzzle);
+    let stuck: Vec<i32> = (0..puzzle.blocks.len() as i32)
+        .filter(|id| {
+            let cell = puzzle.blocks[*id as usize];
+            let on_goal = puzzle.goals.iter().any(|goal| goal.cell == cell && puzzle.is_goal_filled(goal));
+            !is_lost(puzzle, cell) && !on_goal && (dead.contains(&cell) || frozen.contains(id))
+        })
+        .collect();
+    let lost = puzzle.blocks.iter().filter(|cell| is_lost(puzzle, **cell)).count();
+    let spare = puzzle.blocks.len().saturating_sub(puzzle.goals.len());
+    if stuck.len() + lost > spare { Some(stuck) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::puzzle::layered;
+    use super::super::xsb::board;
+
+    const ROOM: &str = "\
+######
+#    #
+# @  #
+#   .#
+######
+";
+
+    #[test]
+    fn corners_off_the_goals_are_dead() {
+        let dead = dead_squares(&board(ROOM));
+        assert!(dead.contains(&GridCell::new(1, 1, 1)));
+        assert!(dead.contains(&GridCell::new(4, 1, 1)));
+        //Along the bottom wall a block can still be pushed onto the goal
+        assert!(!dead.contains(&GridCell::new(2, 1, 3)));
+        assert!(!dead.contains(&GridCell::new(4, 1, 2)));
+        assert!(!dead.contains(&GridCell::new(2, 1, 2)));
+    }
+
+    #[test]
+    fn pulling_leaves_no_dead_squares() {
+        let mut puzzle = board(ROOM);
+        puzzle.rules.pull = true;
+        assert!(dead_squares(&puzzle).is_empty());
+    }
+
+    #[test]
+    fn goals_on_two_heights_leave_no_dead_squares() {
+        let ledge: &[&str] = &["111..", "111..", "111.."];
+        let layers: &[&[&str]] = &[&["00000", "00000", "00000"], ledge, &[".....", ".....", "....."]];
+        let low = layered(layers, (4, 1, 1), &[(1, 2, 1)], &[(3, 1, 1)]);
+        assert_eq!(goal_height(&low), Some(1));
+        assert!(dead_squares(&low).contains(&GridCell::new(4, 1, 0)));
+
+        let both = layered(layers, (4, 1, 1), &[(1, 2, 1), (4, 1, 2)], &[(3, 1, 1), (0, 2, 0)]);
+        assert_eq!(goal_height(&both), None);
+        assert!(dead_squares(&both).is_empty());
+    }
+
+    #[test]
+    fn a_square_of_blocks_off_the_goals_is_frozen() {
+        let puzzle = board("\
+#######
+#  $$ #
+#  $$ #
+#@ .. #
+#  .. #
+#######
+");
+        assert_eq!(frozen_blocks(&puzzle), vec![0, 1, 2, 3]);
+
+        let on_goals = board("\
+#######
+#  ** #
+#  ** #
+#@    #
+#######
+");
+        assert!(frozen_blocks(&on_goals).is_empty());
+    }
+
+    #[test]
+    fn stuck_blocks_are_a_deadlock_when_none_can_be_spared() {
+        let cornered = board("\
+######
+#$ @ #
+#   .#
+######
+");
+        assert_eq!(deadlocked_blocks(&cornered, &dead_squares(&cornered)), Some(vec![0]));
+
+        //With a second block for the goal the cornered one isn't needed
+        let spare = board("\
+######
+#$ @ #
+#  $.#
+######
+");
+        assert_eq!(deadlocked_blocks(&spare, &dead_squares(&spare)), None);
+    }
+
+    #[test]
+    fn a_broken_block_counts_towards_the_deadlock() {
+        let mut puzzle = board("\
+######
+#    #
+# $@ #
+#   .#
+######
+");
+        assert_eq!(deadlocked_blocks(&puzzle, &dead_squares(&puzzle)), None);
+        let player = puzzle.player;
+        puzzle.place(player, &[BROKEN]);
+        assert_eq!(deadlocked_blocks(&puzzle, &dead_squares(&puzzle)), Some(vec![]));
+    }
+}
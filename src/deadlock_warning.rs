@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use super::{grid_physics::*, PlayerSpawned, despawn_screen, game_grid::GridCell};
+use super::deadlock::{dead_squares, deadlocked_blocks};
+use super::history::{undo_redo, MoveHistory};
+use super::input::{read_settings, write_settings, Action, KeyBindings};
+use super::levels;
+use super::puzzle::PuzzleState;
+use super::ui::spawn_button;
+
+const DEADLOCK_FILE: &str = "deadlock.ron";
+
+//Whether the game says so when the level can't be solved any more, set on the controls screen
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeadlockSettings {
+    pub warnings: bool,
+}
+
+impl Default for DeadlockSettings {
+    fn default() -> Self {
+        DeadlockSettings { warnings: true }
+    }
+}
+
+impl DeadlockSettings {
+    pub fn load() -> Self {
+        read_settings(DEADLOCK_FILE).unwrap_or_default()
+    }
+
+    pub fn write(&self) {
+        write_settings(self, DEADLOCK_FILE);
+    }
+}
+
+//The position last checked and the blocks stuck in it, the boxes of those blocks glow red.
+//Found is set when the position is a deadlock, even when no box can be shown for it.
+#[derive(Default)]
+pub struct Deadlock {
+    checked: Option<(GridCell, Vec<GridCell>)>,
+    pub found: bool,
+    pub stuck: Vec<i32>,
+}
+
+//The text and the buttons telling how to get out of a deadlock
+#[derive(Component)]
+pub struct DeadlockPrompt;
+
+//A button of the prompt, with the action it takes
+#[derive(Component, Clone, Copy)]
+pub struct PromptButton(Action);
+
+pub struct DeadlockWarningPlugin;
+
+impl Plugin for DeadlockWarningPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .insert_resource(DeadlockSettings::load())
+        .insert_resource(Deadlock::default())
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes)
+            .with_system(check_deadlock)
+            .with_system(prompt_input.before(undo_redo).before(levels::reset)))
+        .add_system_set(SystemSet::on_exit(PlayerSpawned::Yes)
+            .with_system(despawn_screen::<DeadlockPrompt>)
+            .with_system(forget_deadlock));
+    }
+}
+
+//Looks for a deadlock once the moves are animated, so the warning shows on the boxes where they stopped.
+//The dead squares come from the start of the level, they are found on the floor the player started on.
+fn check_deadlock(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    puzzle: Res<PuzzleState>,
+    history: Res<MoveHistory>,
+    pending: Res<PendingMoves>,
+    block: Res<BlockNextMove>,
+    settings: Res<DeadlockSettings>,
+    bindings: Res<KeyBindings>,
+    mut deadlock: ResMut<Deadlock>,
+    prompts: Query<Entity, With<DeadlockPrompt>>,
+){
+    if block.0 || !pending.0.is_empty() { return; }
+    let position = (puzzle.player, puzzle.blocks.clone());
+    if deadlock.checked.as_ref() == Some(&position) && !settings.is_changed() { return; }
+    deadlock.checked = Some(position);
+
+    let stuck = if settings.warnings && !puzzle.is_solved() {
+        deadlocked_blocks(&puzzle, &dead_squares(history.start().unwrap_or(&puzzle)))
+    } else {
+        None
+    };
+    deadlock.found = stuck.is_some();
+    deadlock.stuck = stuck.unwrap_or_default();
+    //The prompt stays up while the level is still stuck and closes once a move gets it out
+    if !deadlock.found {
+        for entity in prompts.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    } else if prompts.is_empty() {
+        spawn_prompt(&mut commands, &asset_server, &bindings);
+    }
+}
+
+//The buttons of the prompt, or confirm and back while it is up, press the undo or the reset action,
+//so the move history and the level take them back the same way as from their own keys
+fn prompt_input(
+    mut interaction_query: Query<(&Interaction, &PromptButton, &mut Transform), (Changed<Interaction>, With<Button>)>,
+    prompts: Query<Entity, With<DeadlockPrompt>>,
+    mut actions: ResMut<Input<Action>>,
+){
+    let mut chosen = None;
+    for (interaction, button, mut button_transform) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                chosen = Some(button.0);
+            },
+            Interaction::Hovered => {
+                button_transform.scale = Vec3::new(1.2, 1.2, 1.);
+            },
+            Interaction::None => {
+                button_transform.scale = Vec3::new(1., 1., 1.);
+            },
+        }
+    }
+
+    if prompts.is_empty() { return; }
+    if actions.just_pressed(Action::Confirm) {
+        chosen = Some(Action::Undo);
+    } else if actions.just_pressed(Action::Back) {
+        chosen = Some(Action::Reset);
+    }
+    if let Some(action) = chosen {
+        actions.press(action);
+    }
+}
+
+//At the bottom of the screen, the text names the confirm and back keys that pick a button
+fn spawn_prompt(commands: &mut Commands, asset_server: &Res<AssetServer>, bindings: &KeyBindings){
+    let text = format!(
+        "The level can't be solved from here: {} to undo, {} to start over",
+        bindings.key_name(Action::Confirm),
+        bindings.key_name(Action::Back),
+    );
+    let undo_button = spawn_button(commands, asset_server, "UNDO", PromptButton(Action::Undo));
+    let reset_button = spawn_button(commands, asset_server, "RESET", PromptButton(Action::Reset));
+    let buttons = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    }).id();
+    commands.entity(buttons).push_children(&[undo_button, reset_button]);
+
+    let prompt = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect{ bottom: Val::Px(12.), left: Val::Px(0.), ..default() },
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(DeadlockPrompt)
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load("fonts/retganon.ttf"),
+                font_size: 30.0,
+                color: Color::rgb(0.95, 0.4, 0.3),
+            },
+        ));
+    }).id();
+    commands.entity(prompt).add_child(buttons);
+}
+
+//A level started again is checked from its start
+fn forget_deadlock(mut deadlock: ResMut<Deadlock>){
+    *deadlock = Deadlock::default();
+}
@@ -0,0 +1,270 @@
+use super::game_grid::{GameGrid, GridCell, GridCellType};
+use super::level_description::{Level, LevelCamera, LevelRules, LevelSize};
+use super::goals::{Block, BlockColor, Goal};
+use super::tiles::{tile_behavior, TileBehavior, TILES};
+
+//The tiles the editor places, the same ones the game's levels are made of
+pub const FLOOR_TILE: i32 = 0;
+pub const WALL_TILE: i32 = 1;
+
+//What a click of the editor does to the cell under the cursor
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum EditTool {
+    Floor,
+    Wall,
+    //One of the tiles that do more than a floor or a wall, by its id
+    Tile(i32),
+    Block,
+    Goal,
+    Player,
+    Erase,
+}
+
+impl EditTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditTool::Floor => "Floor",
+            EditTool::Wall => "Wall",
+            EditTool::Tile(id) => TILES.iter().find(|(tile, _, _)| *tile == *id).map_or("Tile", |(_, _, name)| *name),
+            EditTool::Block => "Box",
+            EditTool::Goal => "Goal",
+            EditTool::Player => "Player start",
+            EditTool::Erase => "Erase",
+        }
+    }
+}
+
+//The next of the tiles that do more than a floor or a wall, the first one when another tool is picked
+pub fn next_tile_tool(tool: EditTool) -> EditTool {
+    let special: Vec<i32> = TILES.iter()
+        .map(|(id, _, _)| *id)
+        .filter(|id| !matches!(tile_behavior(*id), TileBehavior::Floor | TileBehavior::Wall))
+        .collect();
+    let next = match tool {
+        EditTool::Tile(id) => special.iter().position(|tile| *tile == id).map_or(0, |index| (index + 1) % special.len()),
+        _ => 0,
+    };
+    EditTool::Tile(special[next])
+}
+
+//A floor to build on with the player standing in the middle of it
+pub fn blank_level(name: &str, size: LevelSize) -> Level {
+    let mut grid = GameGrid::new(size.width, size.length, size.height);
+    for x in 0..size.width {
+        for z in 0..size.length {
+            grid.grid.insert(GridCell::new(x, 0, z), Some(GridCellType::Tile(FLOOR_TILE)));
+        }
+    }
+    Level {
+        name: name.to_string(),
+        file: String::new(),
+        collection: None,
+        size,
+        grid,
+        player: GridCell::new(size.width / 2, 1.min(size.height - 1), size.length / 2),
+        blocks: Vec::new(),
+        goals: Vec::new(),
+        camera: LevelCamera::framing(size),
+        rules: LevelRules::default(),
+    }
+}
+
+//Changes the cell under the cursor. Tiles replace the boxes and goals of their cell,
+//a box, a goal or the player start only go where there is no tile.
+pub fn apply(level: &mut Level, tool: EditTool, cell: GridCell) -> Result<(), String> {
+    let has_tile = matches!(level.grid.grid.get(&cell), Some(Some(_)));
+    match tool {
+        EditTool::Floor | EditTool::Wall | EditTool::Tile(_) => {
+            if level.player == cell {
+                return Err("the player starts there".to_string());
+            }
+            let tile = match tool {
+                EditTool::Floor => FLOOR_TILE,
+                EditTool::Tile(id) => id,
+                _ => WALL_TILE,
+            };
+            level.grid.grid.insert(cell, Some(GridCellType::Tile(tile)));
+            level.blocks.retain(|block| block.cell != cell);
+            level.goals.retain(|goal| goal.cell != cell);
+        },
+        EditTool::Block | EditTool::Goal | EditTool::Player if has_tile => {
+            return Err("there is a tile there".to_string());
+        },
+        EditTool::Block => {
+            if level.player == cell {
+                return Err("the player starts there".to_string());
+            }
+            //Placing a box on a box takes it away again
+            if level.blocks.iter().any(|block| block.cell == cell) {
+                level.blocks.retain(|block| block.cell != cell);
+            } else {
+                level.blocks.push(Block::new(cell));
+            }
+        },
+        EditTool::Goal => {
+            if level.goals.iter().any(|goal| goal.cell == cell) {
+                level.goals.retain(|goal| goal.cell != cell);
+            } else {
+                level.goals.push(Goal::new(cell));
+            }
+        },
+        EditTool::Player => {
+            if level.blocks.iter().any(|block| block.cell == cell) {
+                return Err("there is a box there".to_string());
+            }
+            level.player = cell;
+        },
+        EditTool::Erase => {
+            level.grid.grid.insert(cell, None);
+            level.blocks.retain(|block| block.cell != cell);
+            level.goals.retain(|goal| goal.cell != cell);
+        },
+    }
+    Ok(())
+}
+
+//Gives the box and the goal of the cell the next color, both get the same one
+pub fn recolor(level: &mut Level, cell: GridCell) -> Result<(), String> {
+    let block = level.blocks.iter().position(|block| block.cell == cell);
+    let goal = level.goals.iter().position(|goal| goal.cell == cell);
+    let color = match (block, goal) {
+        (Some(block), _) => BlockColor::next(level.blocks[block].color),
+        (None, Some(goal)) => BlockColor::next(level.goals[goal].color),
+        (None, None) => return Err("there is no box or goal there".to_string()),
+    };
+    if let Some(block) = block {
+        level.blocks[block].color = color;
+    }
+    if let Some(goal) = goal {
+        level.goals[goal].color = color;
+    }
+    Ok(())
+}
+
+//Makes the box of the cell the next kind, plain, heavy or fragile
+pub fn change_kind(level: &mut Level, cell: GridCell) -> Result<(), String> {
+    let block = level.blocks.iter_mut().find(|block| block.cell == cell).ok_or_else(|| "there is no box there".to_string())?;
+    block.kind = block.kind.next();
+    Ok(())
+}
+
+//Grows or shrinks the grid from its far side, whatever ends up outside is dropped.
+//The player start can't be dropped, so the grid can't shrink past it.
+pub fn resize(level: &mut Level, size: LevelSize) -> Result<(), String> {
+    if size.width < 1 || size.length < 1 || size.height < 1 {
+        return Err("the level can't be smaller than one cell".to_string());
+    }
+    let max = GridCell::new(size.width - 1, size.height - 1, size.length - 1);
+    let inside = |cell: &GridCell| !cell.out_of_bounds(GridCell::new(0, 0, 0), max);
+    if !inside(&level.player) {
+        return Err("the player start would be outside".to_string());
+    }
+
+    let mut grid = GameGrid::new(size.width, size.length, size.height);
+    for (cell, tile) in level.grid.grid.iter() {
+        if inside(cell) {
+            grid.grid.insert(*cell, *tile);
+        }
+    }
+    level.grid = grid;
+    level.blocks.retain(|block| inside(&block.cell));
+    level.goals.retain(|goal| inside(&goal.cell));
+    level.size = size;
+    level.camera = LevelCamera::framing(size);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level() -> Level {
+        blank_level("test", LevelSize { width: 4, length: 4, height: 2 })
+    }
+
+    fn tile(level: &Level, cell: GridCell) -> Option<i32> {
+        match level.grid.grid.get(&cell) {
+            Some(Some(GridCellType::Tile(id))) => Some(*id),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn a_tile_replaces_the_box_and_the_goal_of_its_cell() {
+        let mut level = level();
+        let cell = GridCell::new(0, 1, 0);
+        apply(&mut level, EditTool::Block, cell).unwrap();
+        apply(&mut level, EditTool::Goal, cell).unwrap();
+        apply(&mut level, EditTool::Wall, cell).unwrap();
+        assert_eq!(tile(&level, cell), Some(WALL_TILE));
+        assert!(level.blocks.is_empty() && level.goals.is_empty());
+        assert_eq!(apply(&mut level, EditTool::Block, cell), Err("there is a tile there".to_string()));
+        apply(&mut level, EditTool::Erase, cell).unwrap();
+        assert_eq!(tile(&level, cell), None);
+    }
+
+    #[test]
+    fn a_second_box_on_a_cell_takes_it_away() {
+        let mut level = level();
+        let cell = GridCell::new(0, 1, 0);
+        apply(&mut level, EditTool::Block, cell).unwrap();
+        assert_eq!(level.blocks.len(), 1);
+        apply(&mut level, EditTool::Block, cell).unwrap();
+        assert!(level.blocks.is_empty());
+    }
+
+    #[test]
+    fn the_player_start_and_a_box_keep_apart() {
+        let mut level = level();
+        let player = level.player;
+        assert_eq!(apply(&mut level, EditTool::Block, player), Err("the player starts there".to_string()));
+        assert_eq!(apply(&mut level, EditTool::Wall, player), Err("the player starts there".to_string()));
+        let cell = GridCell::new(0, 1, 0);
+        apply(&mut level, EditTool::Block, cell).unwrap();
+        assert_eq!(apply(&mut level, EditTool::Player, cell), Err("there is a box there".to_string()));
+        assert_eq!(level.player, player);
+    }
+
+    #[test]
+    fn shrinking_drops_what_ends_up_outside() {
+        let mut level = level();
+        let kept = GridCell::new(0, 1, 0);
+        let dropped = GridCell::new(3, 1, 3);
+        apply(&mut level, EditTool::Block, kept).unwrap();
+        apply(&mut level, EditTool::Block, dropped).unwrap();
+        apply(&mut level, EditTool::Goal, dropped).unwrap();
+        apply(&mut level, EditTool::Wall, GridCell::new(3, 1, 0)).unwrap();
+
+        resize(&mut level, LevelSize { width: 3, length: 3, height: 2 }).unwrap();
+        assert_eq!(level.blocks.iter().map(|block| block.cell).collect::<Vec<_>>(), vec![kept]);
+        assert!(level.goals.is_empty());
+        assert_eq!(level.grid.grid.len(), 3 * 3 * 2);
+        assert!(!level.grid.grid.contains_key(&dropped));
+    }
+
+    #[test]
+    fn growing_back_after_a_shrink_does_not_bring_back_what_was_dropped() {
+        let mut level = level();
+        let wall = GridCell::new(3, 1, 0);
+        apply(&mut level, EditTool::Wall, wall).unwrap();
+        apply(&mut level, EditTool::Goal, GridCell::new(3, 1, 3)).unwrap();
+
+        let size = level.size;
+        resize(&mut level, LevelSize { width: 3, ..size }).unwrap();
+        resize(&mut level, size).unwrap();
+        assert_eq!(level.grid.grid.len(), 4 * 4 * 2);
+        assert_eq!(tile(&level, wall), None);
+        assert_eq!(tile(&level, GridCell::new(3, 0, 0)), None);
+        assert_eq!(tile(&level, GridCell::new(2, 0, 0)), Some(FLOOR_TILE));
+        assert!(level.goals.is_empty());
+    }
+
+    #[test]
+    fn the_grid_cannot_shrink_past_the_player_start_or_below_one_cell() {
+        let mut level = level();
+        let size = level.size;
+        assert_eq!(resize(&mut level, LevelSize { width: 2, ..size }), Err("the player start would be outside".to_string()));
+        assert_eq!(resize(&mut level, LevelSize { height: 0, ..size }), Err("the level can't be smaller than one cell".to_string()));
+        assert_eq!(level.grid.grid.len(), 4 * 4 * 2);
+    }
+}
@@ -0,0 +1,454 @@
+use std::{fs, path::Path};
+use bevy::prelude::*;
+use super::{despawn_screen, GameLevel, GameState, PlayerSpawned, ui::*};
+use super::editing::{apply, blank_level, change_kind, next_tile_tool, recolor, resize, EditTool};
+use super::game_grid::GridCell;
+use super::input::{Action, KeyBindings};
+use super::level_description::{Level, LevelSize, ASSETS_FOLDER, LEVELS_FOLDER};
+use super::level_entities::{spawn_goal, spawn_level, tint_box};
+use super::levels::{CurrentLevel, LevelList};
+use super::validation::check_level;
+
+const CURSOR_COLOR: Color = Color::rgba(1.0, 0.85, 0.1, 0.35);
+const BLOCK_COLOR: Color = Color::rgb(0.53, 0.38, 0.2);
+const PLAYER_COLOR: Color = Color::rgb(0.2, 0.4, 0.8);
+//The size of a new level, a floor with room for walls around it
+const NEW_LEVEL_SIZE: LevelSize = LevelSize { width: 8, length: 6, height: 2 };
+//The levels the editor makes are called like the game's own ones, "levelNN.ron"
+const LEVEL_FILE_PREFIX: &str = "level";
+
+//The tool every tool action picks
+const TOOL_ACTIONS: [(Action, EditTool); 6] = [
+    (Action::FloorTool, EditTool::Floor),
+    (Action::WallTool, EditTool::Wall),
+    (Action::BlockTool, EditTool::Block),
+    (Action::GoalTool, EditTool::Goal),
+    (Action::PlayerTool, EditTool::Player),
+    (Action::EraseTool, EditTool::Erase),
+];
+
+//The help lines, the key bound to each action is shown before its text
+const HELP: [(Action, &str); 20] = [
+    (Action::FloorTool, "floor"),
+    (Action::WallTool, "wall"),
+    (Action::BlockTool, "box"),
+    (Action::GoalTool, "goal"),
+    (Action::PlayerTool, "player"),
+    (Action::SpecialTileTool, "special tiles"),
+    (Action::EraseTool, "erase tool"),
+    (Action::Confirm, "place"),
+    (Action::Erase, "erase"),
+    (Action::Recolor, "color"),
+    (Action::ChangeKind, "box kind"),
+    (Action::LayerDown, "layer down"),
+    (Action::LayerUp, "layer up"),
+    (Action::Resize, "held with moves or layers resizes"),
+    (Action::PlayTest, "play test"),
+    (Action::SaveLevel, "save"),
+    (Action::NewLevel, "new level"),
+    (Action::NextTemplate, "start from the next level"),
+    (Action::PreviousTemplate, "start from the previous level"),
+    (Action::Back, "menu"),
+];
+
+pub struct EditorPlugin;
+
+//Helps to despawn entities
+#[derive(Component)]
+struct OnEditorScreen;
+
+//The entities showing the level, they are made again whenever the level changes
+#[derive(Component)]
+struct EditorScene;
+
+#[derive(Component)]
+struct CursorMarker;
+
+#[derive(Component)]
+struct EditorText;
+
+//The level being edited, it stays while play testing and until another one is started
+pub struct EditorLevel {
+    pub level: Level,
+    //Its file is "levelNN.ron" with this number, the name is "Level NN" until it is saved
+    number: usize,
+    //The level of the level list it was started from
+    template: Option<usize>,
+}
+
+pub struct EditorCursor(GridCell);
+
+//The tool placed on enter and what happened last
+pub struct EditorStatus {
+    tool: EditTool,
+    message: String,
+}
+
+//The current level from before a play test, the level list has the tested level at its end meanwhile
+pub struct PlayTest(pub Option<usize>);
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App){
+        let number = next_level_number();
+        app
+        .insert_resource(EditorLevel { level: blank_level(&level_name(number), NEW_LEVEL_SIZE), number, template: None })
+        .insert_resource(EditorCursor(GridCell::new(0, 1, 0)))
+        .insert_resource(EditorStatus { tool: EditTool::Wall, message: String::new() })
+        .insert_resource(PlayTest(None))
+        // When entering the state, spawn everything needed for this screen
+        .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(setup))
+
+        // Run those systems on update for each frame
+        .add_system_set(SystemSet::on_update(GameState::Editor)
+            .with_system(move_cursor)
+            .with_system(edit_input)
+            .with_system(show_level.after(edit_input))
+            .with_system(show_cursor.after(move_cursor))
+            .with_system(show_status.after(move_cursor).after(edit_input))
+            )
+        .add_system_set(SystemSet::on_update(GameState::Game).with_system(stop_play_test))
+
+        // When exiting the state, despawn everything that was spawned for this screen
+        .add_system_set(SystemSet::on_exit(GameState::Editor)
+            .with_system(despawn_screen::<OnEditorScreen>)
+            .with_system(despawn_screen::<EditorScene>),);
+    }
+}
+
+// spawn everything needed for this screen
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut actions: ResMut<Input<Action>>,
+    bindings: Res<KeyBindings>,
+    mut play_test: ResMut<PlayTest>,
+    mut levels: ResMut<LevelList>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut editor: ResMut<EditorLevel>,
+){
+    //The confirm that opened this screen mustn't place a tile right away
+    actions.clear();
+    //Back from a play test the tested level leaves the level list again
+    if let Some(previous) = play_test.0.take() {
+        levels.0.pop();
+        current_level.0 = previous;
+    }
+    //The level is shown again even if it didn't change
+    editor.set_changed();
+
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.05 })),
+        material: materials.add(StandardMaterial {
+            base_color: CURSOR_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        ..default()
+    }).insert(CursorMarker).insert(OnEditorScreen);
+
+    let hud = spawn_hud_container(&mut commands);
+    commands.entity(hud).insert(OnEditorScreen).insert(Style {
+        size: Size::new(Val::Percent(100.0), Val::Auto),
+        position_type: PositionType::Absolute,
+        position: UiRect{ top: Val::Px(0.), left: Val::Px(0.), ..default() },
+        flex_direction: FlexDirection::ColumnReverse,
+        ..default()
+    });
+    let status = spawn_text(&mut commands, &asset_server, "", EditorText);
+    let help = spawn_text(&mut commands, &asset_server, &help_text(&bindings), OnEditorScreen);
+    commands.entity(hud).push_children(&[status, help]);
+}
+
+//The keys as they are bound on the controls screen, in two lines
+fn help_text(bindings: &KeyBindings) -> String {
+    let pairs: Vec<String> = HELP.iter()
+        .map(|(action, text)| format!("{} {}", bindings.key_name(*action), text))
+        .collect();
+    let half = pairs.len() / 2;
+    format!("{}\n{}", pairs[..half].join("  "), pairs[half..].join("  "))
+}
+
+//The levels folder already has levels up to some number, the next one comes after them
+fn next_level_number() -> usize {
+    let highest = fs::read_dir(LEVELS_FOLDER).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let stem = entry.path().file_stem()?.to_string_lossy().to_string();
+            stem.strip_prefix(LEVEL_FILE_PREFIX)?.parse::<usize>().ok()
+        })
+        .max();
+    highest.unwrap_or(0) + 1
+}
+
+fn level_name(number: usize) -> String {
+    format!("Level {}", number)
+}
+
+fn level_file(number: usize) -> String {
+    format!("{}{:02}", LEVEL_FILE_PREFIX, number)
+}
+
+//The directions move the cursor over the layer and the layer actions go down and up a layer.
+//With resize held they resize the grid.
+fn move_cursor(
+    actions: Res<Input<Action>>,
+    mut cursor: ResMut<EditorCursor>,
+    mut editor: ResMut<EditorLevel>,
+    mut status: ResMut<EditorStatus>,
+){
+    let mut step = GridCell::new(0, 0, 0);
+    if actions.just_pressed(Action::MoveRight) { step.x += 1; }
+    if actions.just_pressed(Action::MoveLeft) { step.x -= 1; }
+    if actions.just_pressed(Action::MoveDown) { step.z += 1; }
+    if actions.just_pressed(Action::MoveUp) { step.z -= 1; }
+    if actions.just_pressed(Action::LayerUp) { step.y += 1; }
+    if actions.just_pressed(Action::LayerDown) { step.y -= 1; }
+    if step == GridCell::new(0, 0, 0) { return; }
+
+    let size = editor.level.size;
+    if actions.pressed(Action::Resize) {
+        let size = LevelSize { width: size.width + step.x, length: size.length + step.z, height: size.height + step.y };
+        match resize(&mut editor.level, size) {
+            Ok(_) => status.message = format!("Resized to {}x{}x{}", size.width, size.length, size.height),
+            Err(error) => status.message = format!("Can't resize: {}", error),
+        }
+    }
+
+    cursor.0 = inside(cursor.0 + step, editor.level.size);
+}
+
+//The cursor stays inside the grid, also after it got smaller
+fn inside(cell: GridCell, size: LevelSize) -> GridCell {
+    GridCell::new(
+        cell.x.clamp(0, size.width - 1),
+        cell.y.clamp(0, size.height - 1),
+        cell.z.clamp(0, size.length - 1),
+    )
+}
+
+fn edit_input(
+    actions: Res<Input<Action>>,
+    mut cursor: ResMut<EditorCursor>,
+    mut editor: ResMut<EditorLevel>,
+    mut status: ResMut<EditorStatus>,
+    mut levels: ResMut<LevelList>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut play_test: ResMut<PlayTest>,
+    mut game_state: ResMut<State<GameState>>,
+){
+    for (action, tool) in TOOL_ACTIONS {
+        if actions.just_pressed(action) {
+            status.tool = tool;
+        }
+    }
+    if actions.just_pressed(Action::SpecialTileTool) {
+        status.tool = next_tile_tool(status.tool);
+    }
+
+    let tool = if actions.just_pressed(Action::Confirm) {
+        Some(status.tool)
+    } else if actions.just_pressed(Action::Erase) {
+        Some(EditTool::Erase)
+    } else {
+        None
+    };
+    if let Some(tool) = tool {
+        if let Err(error) = apply(&mut editor.level, tool, cursor.0) {
+            status.message = format!("Can't place {}: {}", tool.name().to_lowercase(), error);
+        }
+    }
+    if actions.just_pressed(Action::Recolor) {
+        if let Err(error) = recolor(&mut editor.level, cursor.0) {
+            status.message = format!("Can't color: {}", error);
+        }
+    }
+    if actions.just_pressed(Action::ChangeKind) {
+        if let Err(error) = change_kind(&mut editor.level, cursor.0) {
+            status.message = format!("Can't change the box: {}", error);
+        }
+    }
+
+    if actions.just_pressed(Action::NewLevel) {
+        let number = next_level_number();
+        *editor = EditorLevel { level: blank_level(&level_name(number), NEW_LEVEL_SIZE), number, template: None };
+        status.message = "New level".to_string();
+    }
+    let next = actions.just_pressed(Action::NextTemplate);
+    if (next || actions.just_pressed(Action::PreviousTemplate)) && !levels.0.is_empty() {
+        let count = levels.0.len();
+        let index = match (editor.template, next) {
+            (None, _) => 0,
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+        };
+        let mut level = levels.0[index].clone();
+        status.message = format!("Started from {}", level.name);
+        let number = next_level_number();
+        level.name = level_name(number);
+        level.collection = None;
+        *editor = EditorLevel { level, number, template: Some(index) };
+    }
+    if editor.is_changed() {
+        cursor.0 = inside(cursor.0, editor.level.size);
+    }
+
+    //Not control and S by default, the S of WASD with control held resizes the grid
+    if actions.just_pressed(Action::SaveLevel) {
+        status.message = save_level(&editor, &mut levels);
+    }
+
+    if actions.just_pressed(Action::PlayTest) {
+        let mut level = editor.level.clone();
+        level.collection = Some("Play test".to_string());
+        levels.0.push(level);
+        play_test.0 = Some(current_level.0);
+        current_level.0 = levels.0.len() - 1;
+        status.message = String::new();
+        game_state.replace(GameState::Game).unwrap();
+    }
+    else if actions.just_pressed(Action::Back) {
+        game_state.replace(GameState::Menu).unwrap();
+    }
+}
+
+//Writes the level and its layers the way the game's own levels are kept and puts it in the level list
+fn save_level(editor: &EditorLevel, levels: &mut LevelList) -> String {
+    let file = level_file(editor.number);
+    let (description, layers) = editor.level.to_description(&file);
+    let ron = match description.to_ron() {
+        Ok(ron) => ron,
+        Err(error) => return format!("Couldn't save: {}", error),
+    };
+    for (layer, csv) in layers.iter() {
+        let path = Path::new(ASSETS_FOLDER).join(layer);
+        if let Err(error) = fs::write(&path, csv) {
+            return format!("Couldn't write {}: {}", path.display(), error);
+        }
+    }
+    let path = Path::new(LEVELS_FOLDER).join(format!("{}.ron", file));
+    if let Err(error) = fs::write(&path, ron) {
+        return format!("Couldn't write {}: {}", path.display(), error);
+    }
+
+    //Saved again it takes the place it got the first time, new ones go after the game's own levels
+    let mut saved = editor.level.clone();
+    saved.file = format!("{}.ron", file);
+    match levels.0.iter().position(|level| level.collection.is_none() && level.file == saved.file) {
+        Some(index) => levels.0[index] = saved,
+        None => {
+            let index = levels.0.iter().position(|level| level.collection.is_some()).unwrap_or(levels.0.len());
+            levels.0.insert(index, saved);
+        },
+    }
+
+    match check_level(&editor.level).first() {
+        Some(problem) => format!("Saved {}, but {}", path.display(), problem),
+        None => format!("Saved {}", path.display()),
+    }
+}
+
+//Play test again goes back to the editor, finishing the level does too
+fn stop_play_test(
+    actions: Res<Input<Action>>,
+    play_test: Res<PlayTest>,
+    mut game_state: ResMut<State<GameState>>,
+    mut level_state: ResMut<State<GameLevel>>,
+    mut player_spawned: ResMut<State<PlayerSpawned>>,
+){
+    if play_test.0.is_none() || !actions.just_pressed(Action::PlayTest) { return; }
+    if player_spawned.current() != &PlayerSpawned::Yes { return; }
+    leave_play_test(&mut game_state, &mut level_state, &mut player_spawned);
+}
+
+//Takes the tested level down the way the level complete screen does
+pub fn leave_play_test(
+    game_state: &mut ResMut<State<GameState>>,
+    level_state: &mut ResMut<State<GameLevel>>,
+    player_spawned: &mut ResMut<State<PlayerSpawned>>,
+){
+    player_spawned.replace(PlayerSpawned::No).expect("Something went wrong setting player state to no");
+    level_state.replace(GameLevel::None).expect("Something went wrong setting level state to none");
+    game_state.replace(GameState::Editor).unwrap();
+}
+
+//Tiles like in the game, plain cubes for the boxes and the player start
+fn show_level(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    editor: Res<EditorLevel>,
+    scene: Query<Entity, With<EditorScene>>,
+){
+    if !editor.is_changed() { return; }
+    for entity in scene.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let level = &editor.level;
+    spawn_level(&mut commands, &mut meshes, &mut materials, EditorScene, &level.grid);
+    for goal in level.goals.iter() {
+        spawn_goal(&mut commands, &mut meshes, &mut materials, *goal, EditorScene);
+    }
+    let block_mesh = meshes.add(Mesh::from(shape::Cube { size: 0.8 }));
+    for block in level.blocks.iter() {
+        commands.spawn_bundle(PbrBundle {
+            mesh: block_mesh.clone(),
+            material: materials.add(tint_box(BLOCK_COLOR.into(), block.color, block.kind)),
+            transform: Transform::from_translation(block.cell.as_vec3()),
+            ..default()
+        }).insert(EditorScene);
+    }
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Capsule { radius: 0.25, depth: 0.4, ..default() })),
+        material: materials.add(PLAYER_COLOR.into()),
+        transform: Transform::from_translation(level.player.as_vec3()),
+        ..default()
+    }).insert(EditorScene);
+
+    // camera
+    let (x, y, z) = level.camera.position;
+    let (look_x, look_y, look_z) = level.camera.look_at;
+    commands.spawn_bundle(Camera3dBundle {
+        transform: Transform::from_xyz(x, y, z).looking_at(Vec3::new(look_x, look_y, look_z), Vec3::Y),
+        ..default()
+    }).insert(EditorScene);
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(0.0, 8.0, 0.0),
+        ..default()
+    }).insert(EditorScene);
+}
+
+fn show_cursor(cursor: Res<EditorCursor>, mut query: Query<&mut Transform, With<CursorMarker>>){
+    for mut transform in query.iter_mut() {
+        transform.translation = cursor.0.as_vec3();
+    }
+}
+
+fn show_status(
+    cursor: Res<EditorCursor>,
+    editor: Res<EditorLevel>,
+    status: Res<EditorStatus>,
+    mut query: Query<&mut Text, With<EditorText>>,
+){
+    if !cursor.is_changed() && !editor.is_changed() && !status.is_changed() { return; }
+    let size = editor.level.size;
+    let value = format!("{}   Tool: {}   Cursor {},{},{}   Size {}x{}x{}   Boxes {}   Goals {}\n{}",
+        editor.level.name, status.tool.name(),
+        cursor.0.x, cursor.0.y, cursor.0.z,
+        size.width, size.length, size.height,
+        editor.level.blocks.len(), editor.level.goals.len(),
+        status.message);
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
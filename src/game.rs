@@ -1,6 +1,8 @@
 use bevy::{prelude::*, gltf::Gltf};
-use super::{GameState, GameLevel, game_grid::GameGrid,};
-use super::grid_physics::{BlockNextMove, MoveTimer};
+use super::{GameState, GameLevel, game_grid::{GameGrid, GridCell},};
+use super::grid_physics::{BlockNextMove, MoveTimer, PendingMoves, MOVE_SECONDS};
+use super::puzzle::PuzzleState;
+use super::levels::{LevelList, CurrentLevel};
 pub struct GamePlugin;
 
 pub struct GltfHandle {
@@ -33,18 +35,21 @@ fn setup(
     mut level_state: ResMut<State<GameLevel>>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    mut current_level: ResMut<CurrentLevel>,
+    levels: Res<LevelList>,
 ){
     commands.insert_resource(GltfHandle {
         gltf_handle: asset_server.load("warrior_01.glb"),
         box_handle: asset_server.load("box.glb#Scene0"),
     });
 
-    let game_map = GameGrid::new(1, 1, 1);
+    let puzzle = PuzzleState::new(GameGrid::new(1, 1, 1), GridCell::new(0, 0, 0), Vec::new(), Vec::new());
     commands.insert_resource(WinTimer(Timer::from_seconds(2.0, false)));
     commands.insert_resource(PlayerRotationTimer(Timer::from_seconds(0.3, false)));
-    commands.insert_resource(game_map);
+    commands.insert_resource(puzzle);
+    commands.insert_resource(PendingMoves::default());
     commands.insert_resource(BlockNextMove(false));
-    commands.insert_resource(MoveTimer(Timer::from_seconds(0.3, false)));
+    commands.insert_resource(MoveTimer(Timer::from_seconds(MOVE_SECONDS, false)));
     commands.insert_resource(AmbientLight {
         color: Color::rgb_u8(210, 220, 240),
         brightness: 0.3,
@@ -54,8 +59,10 @@ fn setup(
         reset: false,
     });
 
-    
-    level_state.replace(GameLevel::LevelOne).expect("The game level state wasn't setted successfully");
+    //The menu picks the level to start, a save from an older level list may point past its end
+    current_level.0 = current_level.0.min(levels.0.len().saturating_sub(1));
+
+    level_state.replace(GameLevel::Playing).expect("The game level state wasn't setted successfully");
 
 
     
@@ -1,6 +1,8 @@
 use std::{collections::HashMap, ops::{Add, Sub}};
 use bevy::prelude::Vec3;
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone)]
 pub struct GameGrid {
     pub grid: HashMap<GridCell, Option<GridCellType>>,
 }
@@ -34,7 +36,7 @@ impl GameGrid{
             for(z, line) in csv.lines().enumerate(){
                 let indexes = line.split(",").collect::<Vec<&str>>();
                 for(x, str_index) in indexes.into_iter().enumerate(){
-                    let index = str_index.parse::<i32>().unwrap();
+                    let index = str_index.trim().parse::<i32>().unwrap();
                     if index < 0 { continue; }
                     let cell = GridCell{x: x as i32, y: y as i32, z: z as i32};
                     let value = self.grid.get_mut(&cell).unwrap();
@@ -44,9 +46,25 @@ impl GameGrid{
         }
         self
     } 
+
+    //One y level as csv rows, -1 where there is no tile, the way tiles_from_csv reads it
+    pub fn layer_csv(&self, y: i32, width: i32, length: i32) -> String {
+        let mut csv = String::new();
+        for z in 0..length {
+            let row: Vec<String> = (0..width)
+                .map(|x| match self.grid.get(&GridCell{x, y, z}) {
+                    Some(Some(GridCellType::Tile(index))) => index.to_string(),
+                    _ => "-1".to_string(),
+                })
+                .collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
 }
 
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash,)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
 pub struct GridCell {
     pub x: i32, 
     pub y: i32, 
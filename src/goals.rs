@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use super::game_grid::GridCell;
+
+//A colored goal only takes a block of the same color, a goal without a color takes any block
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+pub enum BlockColor {
+    Red,
+    Blue,
+    Green,
+    Yellow,
+}
+
+pub const BLOCK_COLORS: [BlockColor; 4] = [BlockColor::Red, BlockColor::Blue, BlockColor::Green, BlockColor::Yellow];
+
+impl BlockColor {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockColor::Red => "red",
+            BlockColor::Blue => "blue",
+            BlockColor::Green => "green",
+            BlockColor::Yellow => "yellow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BlockColor> {
+        BLOCK_COLORS.into_iter().find(|color| color.name() == name)
+    }
+
+    //The color the editor gives after this one, no color comes after the last one
+    pub fn next(color: Option<BlockColor>) -> Option<BlockColor> {
+        match color {
+            None => Some(BLOCK_COLORS[0]),
+            Some(color) => BLOCK_COLORS.into_iter().skip_while(|other| *other != color).nth(1),
+        }
+    }
+}
+
+//A cell a block has to be pushed onto
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(from = "GoalCell", into = "GoalCell")]
+pub struct Goal {
+    pub cell: GridCell,
+    pub color: Option<BlockColor>,
+}
+
+impl Goal {
+    pub fn new(cell: GridCell) -> Self {
+        Goal { cell, color: None }
+    }
+
+    pub fn accepts(&self, color: Option<BlockColor>) -> bool {
+        self.color.is_none() || self.color == color
+    }
+}
+
+//How a block behaves besides its color
+#[derive(Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+pub enum BlockKind {
+    #[default]
+    Plain,
+    //Only moves on its own, never in a row of blocks pushed together
+    Heavy,
+    //Breaks when it falls and is gone from the level
+    Fragile,
+}
+
+pub const BLOCK_KINDS: [BlockKind; 3] = [BlockKind::Plain, BlockKind::Heavy, BlockKind::Fragile];
+
+impl BlockKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockKind::Plain => "plain",
+            BlockKind::Heavy => "heavy",
+            BlockKind::Fragile => "fragile",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BlockKind> {
+        BLOCK_KINDS.into_iter().find(|kind| kind.name() == name)
+    }
+
+    //The kind the editor gives after this one, going back to plain after the last one
+    pub fn next(&self) -> BlockKind {
+        let index = BLOCK_KINDS.iter().position(|kind| kind == self).unwrap_or(0);
+        BLOCK_KINDS[(index + 1) % BLOCK_KINDS.len()]
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == BlockKind::Plain
+    }
+}
+
+//Where a block starts
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(from = "BlockCell", into = "BlockCell")]
+pub struct Block {
+    pub cell: GridCell,
+    pub color: Option<BlockColor>,
+    pub kind: BlockKind,
+}
+
+impl Block {
+    pub fn new(cell: GridCell) -> Self {
+        Block { cell, color: None, kind: BlockKind::Plain }
+    }
+}
+
+//Goals in a level file, a plain cell like `(x: 1, y: 1, z: 2)` when they have no color
+//and `(x: 1, y: 1, z: 2, color: Some(Red))` when they have one
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct GoalCell {
+    x: i32,
+    y: i32,
+    z: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<BlockColor>,
+}
+
+//Blocks the same way, with `kind: Heavy` or `kind: Fragile` for the blocks that aren't plain
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct BlockCell {
+    x: i32,
+    y: i32,
+    z: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<BlockColor>,
+    #[serde(default, skip_serializing_if = "BlockKind::is_plain")]
+    kind: BlockKind,
+}
+
+impl From<GoalCell> for Goal {
+    fn from(spawn: GoalCell) -> Self {
+        Goal { cell: GridCell::new(spawn.x, spawn.y, spawn.z), color: spawn.color }
+    }
+}
+
+impl From<Goal> for GoalCell {
+    fn from(goal: Goal) -> Self {
+        GoalCell { x: goal.cell.x, y: goal.cell.y, z: goal.cell.z, color: goal.color }
+    }
+}
+
+impl From<BlockCell> for Block {
+    fn from(spawn: BlockCell) -> Self {
+        Block { cell: GridCell::new(spawn.x, spawn.y, spawn.z), color: spawn.color, kind: spawn.kind }
+    }
+}
+
+impl From<Block> for BlockCell {
+    fn from(block: Block) -> Self {
+        BlockCell { x: block.cell.x, y: block.cell.y, z: block.cell.z, color: block.color, kind: block.kind }
+    }
+}
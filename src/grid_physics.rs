@@ -1,5 +1,7 @@
+use std::collections::VecDeque;
 use bevy::prelude::*;
 use super::{game_grid::*, PlayerSpawned, player::Player};
+use super::puzzle::{CellMove, Step, BROKEN};
 
 #[derive(Component)]
 pub struct GridEntity{
@@ -17,33 +19,67 @@ pub struct CurrentPosition(pub GridCell);
 #[derive(Component, Debug)]
 pub struct GridVelocity(pub GridCell);
 
+//Seconds one cell of a move takes at normal speed
+pub const MOVE_SECONDS: f32 = 0.3;
+
 // Newtype to use a `Timer` for this screen as a resource
 #[derive(Deref, DerefMut)]
 pub struct MoveTimer(pub Timer);
 
+//Moves already applied to the puzzle that still have to be animated.
+//Every entry is a group of moves played together, the groups are played one after the other.
+#[derive(Default)]
+pub struct PendingMoves(pub VecDeque<Vec<CellMove>>);
+
+impl PendingMoves {
+    //The push is played first and then everything that moved on by itself, in the order it happened
+    pub fn push_step(&mut self, step: &Step) {
+        self.0.push_back(step.moves.clone());
+        self.0.extend(step.after.iter().cloned());
+    }
+}
+
 pub struct GridPhysicsPlugin;
 
 impl Plugin for GridPhysicsPlugin{
     fn build(&self, app: &mut App){
         app
-        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(move_movable))
-        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(update_grid.after(move_movable)))
-        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(ground_movable.after(update_grid)));
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(start_moves))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(move_movable.after(start_moves)));
     }
 }
 
-pub fn update_grid(mut game_grid: ResMut<GameGrid>, query: Query<(&CurrentPosition, &GridEntity), With<ToMove>>){
-    for (position, grid_entity) in query.iter(){
-        let value = game_grid.grid.get_mut(&position.0).unwrap();
-        *value = Some(grid_entity.cell_type);
+//Gives the entities of the next group of moves their velocity once the previous group is done,
+//a block that breaks is hidden instead
+pub fn start_moves(
+    mut query: Query<(&GridEntity, &mut GridVelocity, &mut ToMove, &mut Visibility)>,
+    mut pending: ResMut<PendingMoves>,
+    mut timer: ResMut<MoveTimer>,
+){
+    if query.iter().any(|(_, _, to_move, _)| to_move.0) { return; }
+    let moves = match pending.0.pop_front() {
+        Some(moves) => moves,
+        None => return,
+    };
+
+    for cell_move in moves {
+        for (grid_entity, mut velocity, mut to_move, mut visibility) in query.iter_mut() {
+            if grid_entity.cell_type != cell_move.mover { continue; }
+            if cell_move.to == BROKEN {
+                visibility.is_visible = false;
+                continue;
+            }
+            velocity.0 = cell_move.to - cell_move.from;
+            to_move.0 = true;
+        }
     }
+    timer.0.reset();
 }
 
 pub fn move_movable(
     mut query: Query<(&mut GridVelocity, &mut ToMove, &mut Transform, &mut CurrentPosition), Without<Player>>,
     time: Res<Time>,
     mut timer: ResMut<MoveTimer>,
-    mut game_grid: ResMut<GameGrid>,
     mut block: ResMut<BlockNextMove>,
 ){
     timer.tick(time.delta());
@@ -54,8 +90,6 @@ pub fn move_movable(
             transform.translation = position.0.as_vec3() + velocity.0.as_vec3() * timer.percent();
             if timer.finished() {
                 to_move.0 = false;
-                let value = game_grid.grid.get_mut(&position.0).unwrap();
-                *value = None;
                 position.0 = position.0 + velocity.0;
                 velocity.0.reset();
                 block.0 = false;
@@ -63,22 +97,3 @@ pub fn move_movable(
         }
     };
 }
-
-fn ground_movable(
-    mut query: Query<(&mut GridVelocity, &mut ToMove, &CurrentPosition, &GridEntity,)>,
-    mut timer: ResMut<MoveTimer>,
-    mut block: ResMut<BlockNextMove>,
-    game_grid: Res<GameGrid>,
-){
-    for (mut velocity, mut to_move, position, grid_entity) in query.iter_mut(){
-        let cell_below = position.0 + GridCell::new(0,-1,0);
-        if cell_below.y < 0 { return;}
-        if to_move.0 { return;}
-        if game_grid.grid.get(&cell_below).unwrap().is_none() {
-            to_move.0 = true;
-            velocity.0 = GridCell::new(0,-1,0);
-            timer.0.reset();
-        }
-    }
-
-}
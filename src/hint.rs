@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use bevy::{prelude::*, tasks::{AsyncComputeTaskPool, Task}};
+use futures_lite::future;
+use super::{grid_physics::*, PlayerSpawned, despawn_screen, game_grid::GridCell};
+use super::game::PlayerRotationTimer;
+use super::history::MoveHistory;
+use super::input::Action;
+use super::player::Player;
+use super::puzzle::{Outcome, PlayerMove, PuzzleState};
+use super::solver::{next_push, solve_until, Solution, SEARCH_LIMIT};
+
+const HINT_COLOR: Color = Color::rgba(1.0, 0.85, 0.1, 0.6);
+
+#[derive(Component)]
+pub struct HintMarker;
+
+//The position the hint markers were made for, they go away as soon as the puzzle changes
+#[derive(Default)]
+pub struct ShownHint(pub Option<(GridCell, Vec<GridCell>)>);
+
+//Steps of the solver's solution still to be played, one after the other
+#[derive(Default)]
+pub struct SolutionPlayback(pub VecDeque<PlayerMove>);
+
+//The solver running in the background, big levels would stop the game for the whole search
+pub struct Search {
+    task: Task<Option<Solution>>,
+    //The position it was started from, a solution for another one is of no use
+    start: PuzzleState,
+    //Whether the solution is played or only shown as a hint
+    play: bool,
+    //Set when the search is dropped, the solver checks it between positions and gives up
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub struct RunningSearch(pub Option<Search>);
+
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .insert_resource(ShownHint::default())
+        .insert_resource(SolutionPlayback::default())
+        .insert_resource(RunningSearch::default())
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(hint_input))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(finish_search.after(hint_input).before(start_moves)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(play_solution.before(start_moves)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(clear_hint))
+        .add_system_set(SystemSet::on_exit(PlayerSpawned::Yes)
+            .with_system(despawn_screen::<HintMarker>)
+            .with_system(stop_playback));
+    }
+}
+
+//Hint shows the next push of a solution, play solution plays all of it and stops it when pressed again.
+//The solver is started in the background, pressing again while it searches starts it over.
+fn hint_input(
+    actions: Res<Input<Action>>,
+    puzzle: Res<PuzzleState>,
+    pending: Res<PendingMoves>,
+    block: Res<BlockNextMove>,
+    mut playback: ResMut<SolutionPlayback>,
+    mut search: ResMut<RunningSearch>,
+){
+    let hint = actions.just_pressed(Action::Hint);
+    let play = actions.just_pressed(Action::PlaySolution);
+    if !hint && !play { return; }
+
+    if play && !playback.0.is_empty() {
+        playback.0.clear();
+        return;
+    }
+    if block.0 || !pending.0.is_empty() || puzzle.is_solved() { return; }
+
+    //Dropping the search that was running tells its solver to give up, so only one runs at a time
+    let start = puzzle.clone();
+    let searched = start.clone();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancelled = cancel.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { solve_until(&searched, SEARCH_LIMIT, &cancelled) });
+    search.0 = Some(Search { task, start, play, cancel });
+}
+
+//Shows the hint or starts playing the solution once the search is done, if the player didn't move meanwhile
+fn finish_search(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    puzzle: Res<PuzzleState>,
+    markers: Query<Entity, With<HintMarker>>,
+    mut shown: ResMut<ShownHint>,
+    mut playback: ResMut<SolutionPlayback>,
+    mut search: ResMut<RunningSearch>,
+){
+    let result = match search.0.as_mut() {
+        Some(running) => match future::block_on(future::poll_once(&mut running.task)) {
+            Some(result) => result,
+            None => return,
+        },
+        None => return,
+    };
+    let finished = search.0.take().unwrap();
+    let start = &finished.start;
+    if start.player != puzzle.player || start.blocks != puzzle.blocks { return; }
+
+    let solution = match result {
+        Some(solution) => solution,
+        None => {
+            info!("No solution found from this position");
+            return;
+        },
+    };
+
+    if finished.play {
+        playback.0 = solution.steps.into_iter().collect();
+        return;
+    }
+
+    for entity in markers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some((id, to)) = next_push(start, &solution) {
+        let from = start.blocks[id as usize];
+        spawn_hint_marker(&mut commands, &mut meshes, &mut materials, from + GridCell::new(0, 1, 0), 0.3);
+        spawn_hint_marker(&mut commands, &mut meshes, &mut materials, to, 0.6);
+        shown.0 = Some((start.player, start.blocks.clone()));
+    }
+}
+
+//A flat marker at the bottom of the cell, over the block to push and on the cell it goes to
+fn spawn_hint_marker(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    position: GridCell,
+    size: f32,
+){
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size })),
+        material: materials.add(StandardMaterial {
+            base_color: HINT_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        transform: Transform::from_xyz(position.x as f32, position.y as f32 - 0.48, position.z as f32),
+        ..default()
+    }).insert(HintMarker);
+}
+
+//Any step, undo or redo makes the shown hint stale
+fn clear_hint(
+    mut commands: Commands,
+    puzzle: Res<PuzzleState>,
+    markers: Query<Entity, With<HintMarker>>,
+    mut shown: ResMut<ShownHint>,
+){
+    let stale = match &shown.0 {
+        Some((player, blocks)) => *player != puzzle.player || *blocks != puzzle.blocks,
+        None => false,
+    };
+    if !stale { return; }
+
+    for entity in markers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    shown.0 = None;
+}
+
+//Plays the next step of the solution like a key press once the previous one is animated
+fn play_solution(
+    mut player_query: Query<(&ToMove, &mut Player)>,
+    mut playback: ResMut<SolutionPlayback>,
+    mut puzzle: ResMut<PuzzleState>,
+    mut pending: ResMut<PendingMoves>,
+    block: Res<BlockNextMove>,
+    mut rotation_timer: ResMut<PlayerRotationTimer>,
+    mut history: ResMut<MoveHistory>,
+){
+    if playback.0.is_empty() || block.0 || !pending.0.is_empty() { return; }
+    let (to_move, mut player) = match player_query.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    if to_move.0 { return; }
+
+    let player_move = playback.0.pop_front().unwrap();
+    rotation_timer.0.reset();
+    player.target_facing = player_move.facing;
+
+    let before = puzzle.clone();
+    match puzzle.play(player_move) {
+        Outcome::Moved(step) => {
+            history.record(before, &step);
+            pending.push_step(&step);
+        },
+        //The solution was made for another position, nothing more of it can be played
+        Outcome::Blocked => playback.0.clear(),
+    }
+}
+
+fn stop_playback(mut playback: ResMut<SolutionPlayback>, mut shown: ResMut<ShownHint>, mut search: ResMut<RunningSearch>){
+    playback.0.clear();
+    shown.0 = None;
+    search.0 = None;
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use super::{game_grid::*, grid_physics::*, PlayerSpawned};
+use super::puzzle::{PuzzleState, Step, BROKEN};
+use super::replay::step_char;
+use super::hint::SolutionPlayback;
+use super::input::Action;
+use super::player::MoveQueue;
+
+//The puzzle as it was right before each player step.
+//The block pushed by a step and any fall that follows it are undone together with the step.
+//Every state keeps the step made from it in replay notation.
+#[derive(Default)]
+pub struct MoveHistory {
+    pub undo: Vec<(PuzzleState, char)>,
+    pub redo: Vec<(PuzzleState, char)>,
+}
+
+impl MoveHistory {
+    //A new move makes the undone moves unreachable
+    pub fn record(&mut self, state: PuzzleState, step: &Step) {
+        self.undo.push((state, step_char(step)));
+        self.redo.clear();
+    }
+
+    //The steps from the start of the level to the current position
+    pub fn replay(&self) -> String {
+        self.undo.iter().map(|(_, c)| *c).collect()
+    }
+
+    //The puzzle as the level started, if anything was played
+    pub fn start(&self) -> Option<&PuzzleState> {
+        self.undo.first().map(|(state, _)| state)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .insert_resource(MoveHistory::default())
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(undo_redo.before(start_moves)));
+    }
+}
+
+//Undo goes one move back and redo one move forward, only while nothing is moving
+pub fn undo_redo(
+    mut query: Query<(&GridEntity, &mut CurrentPosition, &mut Transform, &mut GridVelocity, &mut ToMove, &mut Visibility)>,
+    actions: Res<Input<Action>>,
+    mut history: ResMut<MoveHistory>,
+    mut puzzle: ResMut<PuzzleState>,
+    pending: Res<PendingMoves>,
+    block: Res<BlockNextMove>,
+    playback: Res<SolutionPlayback>,
+    mut queue: ResMut<MoveQueue>,
+){
+    if block.0 || !pending.0.is_empty() || !playback.0.is_empty() { return; }
+    if query.iter().any(|(_, _, _, _, to_move, _)| to_move.0) { return; }
+
+    let undo = actions.just_pressed(Action::Undo);
+    let redo = actions.just_pressed(Action::Redo);
+    if !undo && !redo { return; }
+    //The queued moves were meant for the position being left
+    queue.0.clear();
+
+    let state = if undo { history.undo.pop() } else { history.redo.pop() };
+    let (state, step) = match state {
+        Some(state) => state,
+        None => return,
+    };
+
+    //The step stays with the state on the other side of it
+    let current = std::mem::replace(&mut *puzzle, state);
+    if undo { history.redo.push((current, step)); } else { history.undo.push((current, step)); }
+
+    place_entities(&puzzle, &mut query);
+}
+
+//Puts the player and the blocks where the puzzle has them, the broken blocks out of sight
+pub fn place_entities(
+    puzzle: &PuzzleState,
+    query: &mut Query<(&GridEntity, &mut CurrentPosition, &mut Transform, &mut GridVelocity, &mut ToMove, &mut Visibility)>,
+){
+    for (grid_entity, mut position, mut transform, mut velocity, mut to_move, mut visibility) in query.iter_mut() {
+        let cell = match grid_entity.cell_type {
+            GridCellType::Player => puzzle.player,
+            GridCellType::MovingTile(id) => puzzle.blocks[id as usize],
+            GridCellType::Tile(_) => continue,
+        };
+        visibility.is_visible = cell != BROKEN;
+        position.0 = cell;
+        transform.translation = cell.as_vec3();
+        velocity.0.reset();
+        to_move.0 = false;
+    }
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use super::PlayerSpawned;
+use super::levels::{CurrentLevel, LevelList};
+use super::puzzle::PuzzleState;
+
+//The text beside the reset button with the level and the counters
+#[derive(Component)]
+pub struct HudText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(update_hud));
+    }
+}
+
+pub fn hud_text(levels: &LevelList, current_level: &CurrentLevel, puzzle: &PuzzleState) -> String {
+    let level = &levels.0[current_level.0];
+    if puzzle.is_solved() {
+        return format!("Level {}: {}   Complete in {} moves and {} pushes", current_level.0 + 1, level.name, puzzle.moves, puzzle.pushes);
+    }
+    format!("Level {}: {}   Moves {}   Pushes {}", current_level.0 + 1, level.name, puzzle.moves, puzzle.pushes)
+}
+
+//The counters come from the puzzle, every step, push, undo and redo changes it
+fn update_hud(
+    mut query: Query<&mut Text, With<HudText>>,
+    puzzle: Res<PuzzleState>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+){
+    if !puzzle.is_changed() { return; }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = hud_text(&levels, &current_level, &puzzle);
+    }
+}
@@ -0,0 +1,391 @@
+use std::{collections::{HashMap, HashSet}, fs};
+use bevy::{prelude::*, input::InputSystem};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use super::save_game::save_folder;
+
+const BINDINGS_FILE: &str = "bindings.ron";
+const REPEAT_FILE: &str = "repeat.ron";
+
+//Everything the player can do, the systems read these instead of keys
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Undo,
+    Redo,
+    Reset,
+    Hint,
+    PlaySolution,
+    PlayReplay,
+    PlaybackFaster,
+    PlaybackSlower,
+    Pull,
+    Pause,
+    Confirm,
+    Back,
+    //The editor's own, it also moves the cursor with the directions and places with confirm
+    FloorTool,
+    WallTool,
+    BlockTool,
+    GoalTool,
+    PlayerTool,
+    SpecialTileTool,
+    EraseTool,
+    Erase,
+    Recolor,
+    ChangeKind,
+    LayerUp,
+    LayerDown,
+    Resize,
+    PlayTest,
+    SaveLevel,
+    NewLevel,
+    NextTemplate,
+    PreviousTemplate,
+}
+
+//The actions in the order the controls screen lists them, with the names it shows
+pub const ACTIONS: [(Action, &str); 34] = [
+    (Action::MoveUp, "Move up"),
+    (Action::MoveDown, "Move down"),
+    (Action::MoveLeft, "Move left"),
+    (Action::MoveRight, "Move right"),
+    (Action::Undo, "Undo"),
+    (Action::Redo, "Redo"),
+    (Action::Reset, "Reset level"),
+    (Action::Hint, "Hint"),
+    (Action::PlaySolution, "Play solution"),
+    (Action::PlayReplay, "Play replay"),
+    (Action::PlaybackFaster, "Faster playback"),
+    (Action::PlaybackSlower, "Slower playback"),
+    (Action::Pull, "Pull (hold)"),
+    (Action::Pause, "Pause"),
+    (Action::Confirm, "Confirm"),
+    (Action::Back, "Back"),
+    (Action::FloorTool, "Floor tool"),
+    (Action::WallTool, "Wall tool"),
+    (Action::BlockTool, "Box tool"),
+    (Action::GoalTool, "Goal tool"),
+    (Action::PlayerTool, "Player tool"),
+    (Action::SpecialTileTool, "Special tiles"),
+    (Action::EraseTool, "Erase tool"),
+    (Action::Erase, "Erase"),
+    (Action::Recolor, "Box color"),
+    (Action::ChangeKind, "Box kind"),
+    (Action::LayerUp, "Layer up"),
+    (Action::LayerDown, "Layer down"),
+    (Action::Resize, "Resize (hold)"),
+    (Action::PlayTest, "Play test"),
+    (Action::SaveLevel, "Save level"),
+    (Action::NewLevel, "New level"),
+    (Action::NextTemplate, "Next template"),
+    (Action::PreviousTemplate, "Previous template"),
+];
+//The actions from this one on are the editor's, the controls screen shows them in a column of their own
+pub const FIRST_EDITOR_ACTION: usize = 16;
+
+//The gamepad buttons aren't remapped, the face buttons work like on a handheld
+const GAMEPAD_BUTTONS: [(Action, GamepadButtonType); 13] = [
+    (Action::MoveUp, GamepadButtonType::DPadUp),
+    (Action::MoveDown, GamepadButtonType::DPadDown),
+    (Action::MoveLeft, GamepadButtonType::DPadLeft),
+    (Action::MoveRight, GamepadButtonType::DPadRight),
+    (Action::Confirm, GamepadButtonType::South),
+    (Action::Back, GamepadButtonType::East),
+    (Action::Undo, GamepadButtonType::West),
+    (Action::Reset, GamepadButtonType::North),
+    (Action::Redo, GamepadButtonType::RightTrigger),
+    (Action::Hint, GamepadButtonType::LeftTrigger),
+    (Action::Pause, GamepadButtonType::Start),
+    (Action::PlaySolution, GamepadButtonType::Select),
+    (Action::Pull, GamepadButtonType::RightTrigger2),
+];
+//How far the left stick has to be pushed before it counts as a direction
+const STICK_DEADZONE: f32 = 0.5;
+//The actions pressed again while they are held
+const REPEATED_ACTIONS: [Action; 4] = [Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight];
+
+//A main and an alternate key for every action
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyBindings(pub HashMap<Action, [Option<KeyCode>; 2]>);
+
+//A held direction is pressed again after the delay and then every rate, in seconds
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RepeatSettings {
+    pub delay: f32,
+    pub rate: f32,
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .insert_resource(KeyBindings::load())
+        .insert_resource(RepeatSettings::load())
+        .init_resource::<Input<Action>>()
+        .init_resource::<ActionRepeat>()
+        //Right after bevy reads the keys, so every system of the frame sees the same actions
+        .add_system_to_stage(CoreStage::PreUpdate, update_actions.after(InputSystem));
+    }
+}
+
+impl Default for KeyBindings {
+    //Arrow keys and WASD to move, the rest on the keys the game always had.
+    //Pause and back have keys of their own, so one key press is never both on a screen that reads the two.
+    fn default() -> Self {
+        KeyBindings(HashMap::from([
+            (Action::MoveUp, [Some(KeyCode::Up), Some(KeyCode::W)]),
+            (Action::MoveDown, [Some(KeyCode::Down), Some(KeyCode::S)]),
+            (Action::MoveLeft, [Some(KeyCode::Left), Some(KeyCode::A)]),
+            (Action::MoveRight, [Some(KeyCode::Right), Some(KeyCode::D)]),
+            (Action::Undo, [Some(KeyCode::Z), Some(KeyCode::U)]),
+            (Action::Redo, [Some(KeyCode::Y), None]),
+            (Action::Reset, [Some(KeyCode::R), None]),
+            (Action::Hint, [Some(KeyCode::H), None]),
+            (Action::PlaySolution, [Some(KeyCode::P), None]),
+            (Action::PlayReplay, [Some(KeyCode::L), None]),
+            (Action::PlaybackFaster, [Some(KeyCode::Equals), Some(KeyCode::NumpadAdd)]),
+            (Action::PlaybackSlower, [Some(KeyCode::Minus), Some(KeyCode::NumpadSubtract)]),
+            (Action::Pull, [Some(KeyCode::LShift), Some(KeyCode::RShift)]),
+            (Action::Pause, [Some(KeyCode::Escape), None]),
+            (Action::Confirm, [Some(KeyCode::Return), Some(KeyCode::Space)]),
+            (Action::Back, [Some(KeyCode::Back), None]),
+            (Action::FloorTool, [Some(KeyCode::Key1), None]),
+            (Action::WallTool, [Some(KeyCode::Key2), None]),
+            (Action::BlockTool, [Some(KeyCode::Key3), None]),
+            (Action::GoalTool, [Some(KeyCode::Key4), None]),
+            (Action::PlayerTool, [Some(KeyCode::Key5), None]),
+            (Action::SpecialTileTool, [Some(KeyCode::Key6), None]),
+            (Action::EraseTool, [Some(KeyCode::Key0), None]),
+            (Action::Erase, [Some(KeyCode::Delete), Some(KeyCode::X)]),
+            (Action::Recolor, [Some(KeyCode::C), None]),
+            (Action::ChangeKind, [Some(KeyCode::K), None]),
+            (Action::LayerUp, [Some(KeyCode::E), None]),
+            (Action::LayerDown, [Some(KeyCode::Q), None]),
+            (Action::Resize, [Some(KeyCode::LControl), Some(KeyCode::RControl)]),
+            (Action::PlayTest, [Some(KeyCode::T), None]),
+            (Action::SaveLevel, [Some(KeyCode::F2), None]),
+            (Action::NewLevel, [Some(KeyCode::N), None]),
+            (Action::NextTemplate, [Some(KeyCode::PageDown), None]),
+            (Action::PreviousTemplate, [Some(KeyCode::PageUp), None]),
+        ]))
+    }
+}
+
+impl KeyBindings {
+    //The saved bindings over the defaults, so actions added later still get a key
+    pub fn load() -> Self {
+        let mut bindings = KeyBindings::default();
+        if let Some(saved) = read_settings::<KeyBindings>(BINDINGS_FILE) {
+            bindings.0.extend(saved.0);
+        }
+        bindings
+    }
+
+    pub fn write(&self) {
+        write_settings(self, BINDINGS_FILE);
+    }
+
+    pub fn keys(&self, action: Action) -> [Option<KeyCode>; 2] {
+        self.0.get(&action).copied().unwrap_or([None, None])
+    }
+
+    //The main key of the action as the screens show it, the alternate one when there is no main key
+    pub fn key_name(&self, action: Action) -> String {
+        match self.keys(action).into_iter().flatten().next() {
+            Some(key) => format!("{:?}", key),
+            None => "-".to_string(),
+        }
+    }
+
+    //Puts the key in a slot of the action, none clears the slot. The slot that had the key before gets
+    //the key this slot had, so one key never does two actions and no action loses its key.
+    pub fn bind(&mut self, action: Action, slot: usize, key: Option<KeyCode>) {
+        let replaced = self.keys(action)[slot];
+        if key.is_some() {
+            for bound in self.0.values_mut().flat_map(|keys| keys.iter_mut()) {
+                if *bound == key {
+                    *bound = replaced;
+                }
+            }
+        }
+        let mut keys = self.keys(action);
+        keys[slot] = key;
+        self.0.insert(action, keys);
+    }
+}
+
+impl Default for RepeatSettings {
+    fn default() -> Self {
+        RepeatSettings { delay: 0.35, rate: 0.15 }
+    }
+}
+
+impl RepeatSettings {
+    //The file is written with the defaults the first time, so there is something to edit
+    pub fn load() -> Self {
+        read_settings(REPEAT_FILE).unwrap_or_else(|| {
+            let settings = RepeatSettings::default();
+            write_settings(&settings, REPEAT_FILE);
+            settings
+        })
+    }
+}
+
+//A missing file is fine, a broken one is reported and left alone
+pub fn read_settings<T: DeserializeOwned>(file: &str) -> Option<T> {
+    let path = save_folder().join(file);
+    let text = fs::read_to_string(&path).ok()?;
+    match ron::from_str::<T>(&text) {
+        Ok(settings) => Some(settings),
+        Err(error) => {
+            warn!("Ignoring the settings {}: {}", path.display(), error);
+            None
+        },
+    }
+}
+
+pub fn write_settings<T: Serialize>(settings: &T, file: &str) {
+    let folder = save_folder();
+    let text = match ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(error) => {
+            error!("Couldn't write the settings {}: {}", file, error);
+            return;
+        },
+    };
+    if let Err(error) = fs::create_dir_all(&folder).and_then(|_| fs::write(folder.join(file), text)) {
+        error!("Couldn't write {} in {}: {}", file, folder.display(), error);
+    }
+}
+
+//Held directions with the time until they are pressed again, and the ones pressed again this frame
+#[derive(Default)]
+pub struct ActionRepeat {
+    timers: HashMap<Action, Timer>,
+    repeated: HashSet<Action>,
+}
+
+impl ActionRepeat {
+    //A repeat isn't a new press, the player queue drops it once the key is let go
+    pub fn repeated(&self, action: Action) -> bool {
+        self.repeated.contains(&action)
+    }
+}
+
+//Presses and releases the actions the same way bevy does it for the keys, from the keyboard and every gamepad
+fn update_actions(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    settings: Res<RepeatSettings>,
+    mut repeat: ResMut<ActionRepeat>,
+    mut actions: ResMut<Input<Action>>,
+){
+    actions.clear();
+    let pad_down = gamepad_actions(&gamepads, &buttons, &axes);
+    for (action, _) in ACTIONS {
+        let down = pad_down.contains(&action) || bindings.keys(action).iter().flatten().any(|key| keys.pressed(*key));
+        if down && !actions.pressed(action) {
+            actions.press(action);
+        }
+        else if !down && actions.pressed(action) {
+            actions.release(action);
+        }
+    }
+
+    //A held direction is pressed again, so menus and the player keep moving
+    let ActionRepeat { timers, repeated } = &mut *repeat;
+    repeated.clear();
+    timers.retain(|action, _| actions.pressed(*action));
+    for action in REPEATED_ACTIONS {
+        if !actions.pressed(action) { continue; }
+        let timer = timers.entry(action).or_insert_with(|| Timer::from_seconds(settings.delay, false));
+        timer.tick(time.delta());
+        if timer.finished() {
+            actions.release(action);
+            actions.press(action);
+            repeated.insert(action);
+            *timer = Timer::from_seconds(settings.rate, false);
+        }
+    }
+}
+
+//The actions held on any connected gamepad, the left stick counts as the direction it is pushed the most
+fn gamepad_actions(gamepads: &Gamepads, buttons: &Input<GamepadButton>, axes: &Axis<GamepadAxis>) -> HashSet<Action> {
+    let mut down = HashSet::new();
+    for gamepad in gamepads.iter() {
+        for (action, button_type) in GAMEPAD_BUTTONS {
+            if buttons.pressed(GamepadButton(*gamepad, button_type)) {
+                down.insert(action);
+            }
+        }
+
+        let x = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+        if x.abs().max(y.abs()) < STICK_DEADZONE { continue; }
+        let action = if x.abs() > y.abs() {
+            if x > 0.0 { Action::MoveRight } else { Action::MoveLeft }
+        } else if y > 0.0 {
+            Action::MoveUp
+        } else {
+            Action::MoveDown
+        };
+        down.insert(action);
+    }
+    down
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_key_taken_from_another_action_swaps_with_the_replaced_key() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Redo, 0, Some(KeyCode::Z));
+        assert_eq!(bindings.keys(Action::Redo), [Some(KeyCode::Z), None]);
+        assert_eq!(bindings.keys(Action::Undo), [Some(KeyCode::Y), Some(KeyCode::U)]);
+    }
+
+    #[test]
+    fn a_key_moved_to_an_empty_slot_leaves_the_other_action_without_it() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Redo, 1, Some(KeyCode::U));
+        assert_eq!(bindings.keys(Action::Redo), [Some(KeyCode::Y), Some(KeyCode::U)]);
+        assert_eq!(bindings.keys(Action::Undo), [Some(KeyCode::Z), None]);
+    }
+
+    #[test]
+    fn binding_the_key_a_slot_already_has_changes_nothing() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Undo, 0, Some(KeyCode::Z));
+        assert_eq!(bindings.0, KeyBindings::default().0);
+    }
+
+    #[test]
+    fn clearing_a_slot_leaves_the_other_actions_alone() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Undo, 0, None);
+        assert_eq!(bindings.keys(Action::Undo), [None, Some(KeyCode::U)]);
+        assert_eq!(bindings.keys(Action::Redo), [Some(KeyCode::Y), None]);
+        assert_eq!(bindings.key_name(Action::Undo), "U");
+    }
+
+    #[test]
+    fn the_bindings_read_back_the_same_from_ron() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Erase, 0, Some(KeyCode::Back));
+        bindings.bind(Action::Reset, 1, None);
+        let text = ron::ser::to_string_pretty(&bindings, ron::ser::PrettyConfig::default()).unwrap();
+        let again: KeyBindings = ron::from_str(&text).unwrap();
+        assert_eq!(again.0, bindings.0);
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use super::{despawn_screen, GameLevel, GameState, PlayerSpawned, ui::*};
+use super::game::LevelReset;
+use super::levels::{CurrentLevel, LevelClock, LevelList};
+use super::puzzle::PuzzleState;
+use super::save_game::SaveGame;
+use super::input::Action;
+
+pub struct LevelCompletePlugin;
+
+//Helps to despawn entities
+#[derive(Component)]
+struct OnCompleteScreen;
+
+//Enum values representing all the buttons
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, FromPrimitive)]
+pub enum CompleteButtonType{
+    Next,
+    Retry,
+    LevelSelect,
+}
+
+//The current selected button value
+pub struct SelectedCompleteButton(CompleteButtonType);
+
+#[derive(Component)]
+pub struct CompleteButton(CompleteButtonType);
+
+impl Plugin for LevelCompletePlugin{
+    fn build(&self, app: &mut App){
+        app
+        // When entering the state, spawn everything needed for this screen
+        .add_system_set(SystemSet::on_enter(GameLevel::Complete).with_system(setup))
+        // Run those systems on update for each frame
+        .add_system_set(SystemSet::on_update(GameLevel::Complete)
+            .with_system(mark_selected)
+            .with_system(next_previous_button)
+            .with_system(select_button)
+            .with_system(mouse_on_button)
+            )
+        // When exiting the state, despawn everything that was spawned for this screen
+        .add_system_set(SystemSet::on_exit(GameLevel::Complete)
+            .with_system(despawn_screen::<OnCompleteScreen>),);
+    }
+}
+
+// spawn everything needed for this screen
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    puzzle: Res<PuzzleState>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+    clock: Res<LevelClock>,
+    save: Res<SaveGame>,
+){
+    //A semitransparent black container that covers the finished level
+    let shadow = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(1920.0), Val::Px(1080.0)),
+            margin: UiRect::all(Val::Auto),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::rgba(0.,0.,0.,0.8).into(),
+        transform: Transform::from_translation(Vec3::new(0.0,0.0, 100.0)),
+        ..default()
+    }).insert(OnCompleteScreen).id();
+
+    let level = &levels.0[current_level.0];
+    let seconds = clock.0 as u32;
+    let best = match save.levels.get(&level.id()) {
+        Some(progress) => format!("Best: {} moves, {} pushes", progress.best_moves.unwrap_or(puzzle.moves), progress.best_pushes.unwrap_or(puzzle.pushes)),
+        None => String::new(),
+    };
+    let lines = [
+        format!("Level {} complete: {}", current_level.0 + 1, level.name),
+        format!("Moves {}   Pushes {}   Time {}:{:02}", puzzle.moves, puzzle.pushes, seconds / 60, seconds % 60),
+        best,
+    ];
+    let summary = spawn_menu_container(&mut commands);
+    commands.entity(summary).insert(Style {
+        margin: UiRect{ top: Val::Px(300.), left: Val::Auto, bottom: Val::Px(24.), right: Val::Auto },
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::Center,
+        ..default()
+    });
+    for line in lines {
+        let text = spawn_text(&mut commands, &asset_server, &line, OnCompleteScreen);
+        commands.entity(summary).add_child(text);
+    }
+    commands.entity(shadow).add_child(summary);
+
+    //After the last level of the game or of a collection there are only the credits left
+    let next = if levels.has_next(current_level.0) { "NEXT" } else { "CREDITS" };
+    let button_texts = vec![next, "RETRY", "LEVELS"];
+
+    //A container entity containing all the buttons
+    let node = spawn_menu_container(&mut commands);
+    commands.entity(node).insert(Style {
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::Center,
+        ..default()
+    });
+
+    //Spawn a new button for each string in vector
+    for text in button_texts{
+
+        //Helper function gets the enum value from text
+        let menu_type = menu_item_from_text(text);
+        let button = spawn_button(&mut commands, &asset_server, text, menu_type,);
+        commands.entity(node).add_child(button);
+    }
+    commands.insert_resource(SelectedCompleteButton(CompleteButtonType::Next));
+    commands.entity(shadow).add_child(node);
+}
+
+//Helper function gets the enum value from text
+fn menu_item_from_text(text: &str) -> CompleteButton {
+    match text {
+        "RETRY" => {
+            CompleteButton(CompleteButtonType::Retry)
+        },
+        "LEVELS" => {
+            CompleteButton(CompleteButtonType::LevelSelect)
+        },
+        _ => {
+            CompleteButton(CompleteButtonType::Next)
+        }
+    }
+}
+
+//Change the selected button scale so you know with is it
+fn mark_selected(selected: Res<SelectedCompleteButton>, mut query: Query<(&mut Transform, &CompleteButton)>){
+    for (mut transform, menu_button) in query.iter_mut(){
+        if selected.0 == menu_button.0{
+            transform.scale = Vec3::new(1.2, 1.2, 1.);
+        }
+        else{
+            transform.scale = Vec3::new(1., 1., 1.);
+        }
+    }
+}
+
+//Move between button values with the arrow keys (or what ever is the up/down keys)
+fn next_previous_button(mut selected: ResMut<SelectedCompleteButton>,actions: Res<Input<Action>>){
+    let mut int_selection= selected.0 as u8;
+
+    if actions.just_pressed(Action::MoveDown) {
+        int_selection = if int_selection < 2 { int_selection + 1 }else{ 0 };
+    }
+
+    if actions.just_pressed(Action::MoveUp) {
+        int_selection = if int_selection > 0 { int_selection - 1 }else{ 2 };
+    }
+    selected.0 = CompleteButtonType::from_u8(int_selection).unwrap();
+}
+
+fn select_button(selected: Res<SelectedCompleteButton>,
+    actions: Res<Input<Action>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut level_state: ResMut<State<GameLevel>>,
+    mut player_spawn_state: ResMut<State<PlayerSpawned>>,
+    mut level_reset: ResMut<LevelReset>,
+    mut current_level: ResMut<CurrentLevel>,
+    levels: Res<LevelList>,
+){
+    if actions.just_pressed(Action::Confirm){
+        accept_selection(selected.0, &mut game_state, &mut level_state, &mut player_spawn_state, &mut level_reset, &mut current_level, &levels);
+    }
+}
+
+fn mouse_on_button(
+    interaction_query: Query<
+        (&Interaction,&CompleteButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut selected: ResMut<SelectedCompleteButton>,
+    mut game_state: ResMut<State<GameState>>,
+    mut level_state: ResMut<State<GameLevel>>,
+    mut player_spawn_state: ResMut<State<PlayerSpawned>>,
+    mut level_reset: ResMut<LevelReset>,
+    mut current_level: ResMut<CurrentLevel>,
+    levels: Res<LevelList>,
+) {
+    for (interaction, menu_button)  in interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => {
+                accept_selection(menu_button.0, &mut game_state, &mut level_state, &mut player_spawn_state, &mut level_reset, &mut current_level, &levels);
+            },
+            Interaction::Hovered => {
+                selected.0 = menu_button.0;
+            },
+            Interaction::None => {},
+        }
+    }
+}
+
+//Leaving the screen takes down the finished level, next and retry load a level again through the level reset
+fn accept_selection(
+    selected: CompleteButtonType,
+    game_state: &mut ResMut<State<GameState>>,
+    level_state: &mut ResMut<State<GameLevel>>,
+    player_spawn_state: &mut ResMut<State<PlayerSpawned>>,
+    level_reset: &mut ResMut<LevelReset>,
+    current_level: &mut ResMut<CurrentLevel>,
+    levels: &Res<LevelList>,
+){
+    player_spawn_state.replace(PlayerSpawned::No).expect("Something went wrong setting player state to no");
+    level_state.replace(GameLevel::None).expect("Something went wrong setting level state to none");
+    match selected {
+        CompleteButtonType::Next if !levels.has_next(current_level.0) => {
+            game_state.replace(GameState::Credits).unwrap();
+        },
+        CompleteButtonType::Next => {
+            current_level.0 += 1;
+            level_reset.reset = true;
+            level_reset.level = GameLevel::Playing;
+        },
+        CompleteButtonType::Retry => {
+            level_reset.reset = true;
+            level_reset.level = GameLevel::Playing;
+        },
+        CompleteButtonType::LevelSelect => {
+            game_state.replace(GameState::LevelSelect).unwrap();
+        },
+    }
+}
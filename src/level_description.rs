@@ -0,0 +1,316 @@
+use std::{collections::HashSet, fmt, fs, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+use super::game_grid::{GameGrid, GridCell};
+use super::goals::{Block, Goal};
+use super::tiled_map::load_tmx;
+use super::xsb::load_xsb;
+
+//The folder the game scans for .ron, .tmx and .xsb level files, they are played in file name order
+pub const LEVELS_FOLDER: &str = "assets/levels";
+//Layer paths inside a level file are relative to this folder
+pub const ASSETS_FOLDER: &str = "assets";
+
+//A level as it is written in a .ron file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LevelDescription {
+    pub name: String,
+    pub size: LevelSize,
+    //One csv file per y level, starting from the ground
+    pub layers: Vec<String>,
+    pub player: GridCell,
+    //Plain cells, or cells with a color for colored blocks and the goals that only take them
+    pub blocks: Vec<Block>,
+    pub goals: Vec<Goal>,
+    pub camera: LevelCamera,
+    //Left out of the file for the usual sokoban rules
+    #[serde(default, skip_serializing_if = "LevelRules::is_default")]
+    pub rules: LevelRules,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct LevelSize {
+    pub width: i32,
+    pub length: i32,
+    pub height: i32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct LevelCamera {
+    pub position: (f32, f32, f32),
+    pub look_at: (f32, f32, f32),
+}
+
+impl LevelCamera {
+    //A camera looking at the middle of the grid from the front, for levels that don't set one
+    pub fn framing(size: LevelSize) -> Self {
+        let center_x = (size.width - 1) as f32 / 2.0;
+        let center_z = (size.length - 1) as f32 / 2.0;
+        LevelCamera {
+            position: (center_x, size.length as f32 + 2.0, center_z + 5.0),
+            look_at: (center_x, 0.0, center_z),
+        }
+    }
+}
+
+//The rules a level plays with, a level file only lists the ones it changes
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LevelRules {
+    //How many blocks in a row a single step can push
+    pub chain: usize,
+    //Holding the pull key while stepping away from a block brings it along
+    pub pull: bool,
+    //Pushed blocks slide on like on ice until something stops them
+    pub slide: bool,
+}
+
+impl Default for LevelRules {
+    fn default() -> Self {
+        LevelRules { chain: 1, pull: false, slide: false }
+    }
+}
+
+impl LevelRules {
+    pub fn is_default(&self) -> bool {
+        *self == LevelRules::default()
+    }
+}
+
+//A level ready to be played, with its grid already filled from the layers
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    //The name of the file the level was loaded from, empty for levels made in the game
+    pub file: String,
+    //The imported collection the level comes from, none for the game's own levels
+    pub collection: Option<String>,
+    pub size: LevelSize,
+    pub grid: GameGrid,
+    pub player: GridCell,
+    pub blocks: Vec<Block>,
+    pub goals: Vec<Goal>,
+    pub camera: LevelCamera,
+    pub rules: LevelRules,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            LevelError::Parse(path, error) => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl LevelDescription {
+    pub fn from_file(path: &Path) -> Result<Self, LevelError> {
+        let text = fs::read_to_string(path).map_err(|e| LevelError::Io(path.to_path_buf(), e))?;
+        ron::from_str(&text).map_err(|e| LevelError::Parse(path.to_path_buf(), e.to_string()))
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        //Every cell on one line, like in the hand written levels
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().depth_limit(2)).map_err(|e| e.to_string())
+    }
+
+    //Reads the csv layers and builds the playable level
+    pub fn load(self, path: &Path) -> Result<Level, LevelError> {
+        if self.layers.len() != self.size.height as usize {
+            return Err(LevelError::Parse(path.to_path_buf(),
+                format!("{} layers but the level is {} high", self.layers.len(), self.size.height)));
+        }
+        let mut tiles = Vec::new();
+        for layer in self.layers.iter() {
+            let layer_path = Path::new(ASSETS_FOLDER).join(layer);
+            let csv = fs::read_to_string(&layer_path).map_err(|e| LevelError::Io(layer_path.clone(), e))?;
+            check_layer(&csv, self.size).map_err(|e| LevelError::Parse(layer_path, e))?;
+            tiles.push(csv);
+        }
+        let grid = GameGrid::new(self.size.width, self.size.length, self.size.height).tiles_from_csv(tiles);
+
+        Ok(Level {
+            name: self.name,
+            file: String::new(),
+            collection: None,
+            size: self.size,
+            grid,
+            player: self.player,
+            blocks: self.blocks,
+            goals: self.goals,
+            camera: self.camera,
+            rules: self.rules,
+        })
+    }
+}
+
+impl Level {
+    //The file and the name together, no two loaded levels share it. The save game keeps the progress by it.
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.file, self.name)
+    }
+
+    //The level as a .ron description with its csv layers, the layers are named after the file
+    //and go in the maps folder like the game's own levels
+    pub fn to_description(&self, file: &str) -> (LevelDescription, Vec<(String, String)>) {
+        let layers: Vec<(String, String)> = (0..self.size.height)
+            .map(|y| (format!("maps/{}{}.csv", file, y), self.grid.layer_csv(y, self.size.width, self.size.length)))
+            .collect();
+        let description = LevelDescription {
+            name: self.name.clone(),
+            size: self.size,
+            layers: layers.iter().map(|(path, _)| path.clone()).collect(),
+            player: self.player,
+            blocks: self.blocks.clone(),
+            goals: self.goals.clone(),
+            camera: self.camera,
+            rules: self.rules,
+        };
+        (description, layers)
+    }
+}
+
+//A name that can be used in a file name: lower case letters, digits and dashes, anything else an underscore
+pub fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+//A csv layer has to be exactly as wide and as long as the level, filling the grid would panic otherwise
+fn check_layer(csv: &str, size: LevelSize) -> Result<(), String> {
+    let rows: Vec<&str> = csv.lines().collect();
+    if rows.len() != size.length as usize {
+        return Err(format!("{} rows but the level is {} long", rows.len(), size.length));
+    }
+    for (z, row) in rows.iter().enumerate() {
+        let indexes: Vec<&str> = row.split(',').collect();
+        if indexes.len() != size.width as usize {
+            return Err(format!("row {} has {} tiles but the level is {} wide", z, indexes.len(), size.width));
+        }
+        if let Some(index) = indexes.iter().find(|index| index.trim().parse::<i32>().is_err()) {
+            return Err(format!("row {} has {:?}, which isn't a tile index", z, index));
+        }
+    }
+    Ok(())
+}
+
+//Loads every level file found in the folder, sorted by file name.
+//A file that doesn't load is left out and given back with the error, so one bad file doesn't lose the rest.
+pub fn load_levels(folder: &str) -> Result<(Vec<Level>, Vec<LevelError>), LevelError> {
+    let paths = level_files(folder)?;
+
+    let mut levels = Vec::new();
+    let mut errors = Vec::new();
+    let mut ids = HashSet::new();
+    for path in paths {
+        match load_level_file(&path) {
+            Ok(loaded) => for level in loaded {
+                //A second level of the same name in a file would share the progress of the first
+                if ids.insert(level.id()) {
+                    levels.push(level);
+                } else {
+                    errors.push(LevelError::Parse(path.clone(), format!("a second level named \"{}\"", level.name)));
+                }
+            },
+            Err(error) => errors.push(error),
+        }
+    }
+    Ok((levels, errors))
+}
+
+//All the levels of a file, a .xsb collection can have many
+pub fn load_level_file(path: &Path) -> Result<Vec<Level>, LevelError> {
+    let mut levels = if path.extension().is_some_and(|ext| ext == "xsb") {
+        load_xsb(path)?
+    } else {
+        vec![load_level(path)?]
+    };
+    let file = path.file_name().map_or(String::new(), |file| file.to_string_lossy().to_string());
+    for level in levels.iter_mut() {
+        level.file = file.clone();
+    }
+    Ok(levels)
+}
+
+//Loads a single .ron or .tmx level file
+pub fn load_level(path: &Path) -> Result<Level, LevelError> {
+    if path.extension().is_some_and(|ext| ext == "tmx") {
+        return load_tmx(path);
+    }
+    LevelDescription::from_file(path)?.load(path)
+}
+
+//The .ron, .tmx and .xsb files of the folder, sorted by file name
+pub fn level_files(folder: &str) -> Result<Vec<PathBuf>, LevelError> {
+    let entries = fs::read_dir(folder).map_err(|e| LevelError::Io(PathBuf::from(folder), e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| LevelError::Io(PathBuf::from(folder), e))?.path();
+        if path.extension().is_some_and(|ext| ext == "ron" || ext == "tmx" || ext == "xsb") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A folder of its own in the temp folder, emptied first
+    fn level_folder(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("carrier_clone_{}", name));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        for (file, text) in files {
+            fs::write(folder.join(file), text).unwrap();
+        }
+        folder
+    }
+
+    const BOARD: &str = "\
+#####
+#@$.#
+#####
+";
+
+    #[test]
+    fn levels_of_the_same_name_in_two_files_are_told_apart_by_the_file() {
+        let titled = format!("{}Title: Same\n", BOARD);
+        let folder = level_folder("two_files", &[("a.xsb", &titled), ("b.xsb", &titled)]);
+        let (levels, errors) = load_levels(folder.to_str().unwrap()).unwrap();
+        assert!(errors.is_empty());
+        let ids: Vec<String> = levels.iter().map(|level| level.id()).collect();
+        assert_eq!(ids, vec!["a.xsb/Same".to_string(), "b.xsb/Same".to_string()]);
+    }
+
+    #[test]
+    fn a_second_level_of_the_same_name_in_a_file_is_skipped() {
+        let twice = format!("{}Title: Same\n\n{}Title: Same\n", BOARD, BOARD);
+        let folder = level_folder("same_file", &[("a.xsb", &twice)]);
+        let (levels, errors) = load_levels(folder.to_str().unwrap()).unwrap();
+        assert_eq!(levels.len(), 1);
+        assert_eq!(errors.len(), 1);
+    }
+
+    const SIZE: LevelSize = LevelSize { width: 3, length: 2, height: 1 };
+
+    #[test]
+    fn a_layer_of_the_level_size_is_fine() {
+        assert_eq!(check_layer("0,0,1\n1,-1,0\n", SIZE), Ok(()));
+    }
+
+    #[test]
+    fn a_layer_of_the_wrong_size_is_refused() {
+        assert_eq!(check_layer("0,0,0\n", SIZE), Err("1 rows but the level is 2 long".to_string()));
+        assert_eq!(check_layer("0,0,0\n0,0\n", SIZE), Err("row 1 has 2 tiles but the level is 3 wide".to_string()));
+        assert_eq!(check_layer("0,0,0\n0,x,0\n", SIZE), Err("row 1 has \"x\", which isn't a tile index".to_string()));
+    }
+}
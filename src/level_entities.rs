@@ -1,5 +1,10 @@
+use std::{collections::HashMap, f32::consts::{FRAC_PI_2, FRAC_PI_4, PI}};
 use bevy::prelude::*;
 use super::game_grid::{GameGrid, GridCell, GridCellType};
+use super::goals::{BlockColor, BlockKind, Goal};
+use super::puzzle::{Facing, PuzzleState};
+use super::deadlock_warning::Deadlock;
+use super::tiles::{tile_behavior, TileBehavior};
 use super::grid_physics::{GridEntity, CurrentPosition, GridVelocity, ToMove,};
 use super::game::GltfHandle;
 
@@ -26,69 +31,241 @@ pub fn spawn_blocks(
     .insert(screen.clone());
 }
 
-pub fn fill_grid(grid:&mut ResMut<GameGrid>, tiles: Vec<String>, width: i32, length: i32, heigth: i32){
-    grid.reset(width, length, heigth);
-    for (y, csv) in tiles.iter().enumerate(){
-        for(z, line) in csv.lines().enumerate(){
-            let indexes = line.split(",").collect::<Vec<&str>>();
-            for(x, str_index) in indexes.into_iter().enumerate(){
-                let index = str_index.parse::<i32>().unwrap();
-                if index < 0 { continue; }
-                let cell = GridCell{x: x as i32, y: y as i32, z: z as i32};
-                let value = grid.grid.get_mut(&cell).unwrap();
-                *value = Some(GridCellType::Tile(index));
+//How a block color looks on the boxes and the goals
+pub fn block_tint(color: BlockColor) -> Color {
+    match color {
+        BlockColor::Red => Color::rgb(0.8, 0.2, 0.2),
+        BlockColor::Blue => Color::rgb(0.2, 0.35, 0.85),
+        BlockColor::Green => Color::rgb(0.2, 0.7, 0.25),
+        BlockColor::Yellow => Color::rgb(0.9, 0.8, 0.2),
+    }
+}
+
+//Small plane marking a cell where a block has to be pushed, black or in the color the goal takes
+pub fn spawn_goal(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    goal: Goal,
+    screen: impl Component + Clone,
+){
+    let position = goal.cell;
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 0.20 })),
+        material: materials.add(goal.color.map_or(Color::BLACK, block_tint).into()),
+        transform: Transform::from_xyz(position.x as f32, position.y as f32 - 0.49, position.z as f32),
+        ..default()
+    }).insert(screen.clone());
+}
+
+//A box over the material it starts from: in its color, dark when it is heavy and see through when it is fragile
+pub fn tint_box(mut material: StandardMaterial, color: Option<BlockColor>, kind: BlockKind) -> StandardMaterial {
+    if let Some(color) = color {
+        material.base_color = block_tint(color);
+    }
+    match kind {
+        BlockKind::Heavy => {
+            let [r, g, b, a] = material.base_color.as_rgba_f32();
+            material.base_color = Color::rgba(r * 0.45, g * 0.45, b * 0.45, a);
+            material.metallic = 0.6;
+        },
+        BlockKind::Fragile => {
+            material.base_color.set_a(0.55);
+            material.alpha_mode = AlphaMode::Blend;
+        },
+        BlockKind::Plain => {},
+    }
+    material
+}
+
+//Boxes glow a little while they stand on a goal that takes them, and red when they are stuck for good
+const ON_GOAL_GLOW: Color = Color::rgb(0.35, 0.3, 0.1);
+const STUCK_GLOW: Color = Color::rgb(0.6, 0.05, 0.05);
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+struct BoxLook {
+    color: Option<BlockColor>,
+    kind: BlockKind,
+    on_goal: bool,
+    stuck: bool,
+}
+
+//The materials of the boxes, made once for every look from the material box.glb comes with
+#[derive(Default)]
+pub struct BoxMaterials {
+    base: Option<StandardMaterial>,
+    looks: HashMap<BoxLook, Handle<StandardMaterial>>,
+}
+
+impl BoxMaterials {
+    fn material(&mut self, look: BoxLook, current: &Handle<StandardMaterial>, materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+        //The first box mesh seen still has the material of the scene
+        if self.base.is_none() {
+            self.base = materials.get(current).cloned();
+        }
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => return current.clone(),
+        };
+        self.looks.entry(look).or_insert_with(move || {
+            let mut material = tint_box(base, look.color, look.kind);
+            if look.on_goal {
+                material.emissive = ON_GOAL_GLOW;
+            }
+            if look.stuck {
+                material.emissive = STUCK_GLOW;
+            }
+            materials.add(material)
+        }).clone()
+    }
+}
+
+//Gives the meshes of every box scene the look of its block. The scene meshes show up a few frames
+//after the box is spawned, so they are checked every frame and only changed when the look changes.
+pub fn tint_boxes(
+    puzzle: Res<PuzzleState>,
+    deadlock: Res<Deadlock>,
+    mut box_materials: ResMut<BoxMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    blocks: Query<(&GridEntity, &Children)>,
+    children: Query<&Children>,
+    mut mesh_materials: Query<&mut Handle<StandardMaterial>>,
+){
+    for (entity, block_children) in blocks.iter() {
+        let id = match entity.cell_type {
+            GridCellType::MovingTile(id) => id as usize,
+            _ => continue,
+        };
+        let cell = puzzle.blocks[id];
+        let look = BoxLook {
+            color: puzzle.colors[id],
+            kind: puzzle.kinds[id],
+            on_goal: puzzle.goals.iter().any(|goal| goal.cell == cell && puzzle.is_goal_filled(goal)),
+            stuck: deadlock.stuck.contains(&(id as i32)),
+        };
+        let mut descendants: Vec<Entity> = block_children.iter().copied().collect();
+        while let Some(descendant) = descendants.pop() {
+            if let Ok(grand_children) = children.get(descendant) {
+                descendants.extend(grand_children.iter());
+            }
+            if let Ok(mut handle) = mesh_materials.get_mut(descendant) {
+                let material = box_materials.material(look, &handle, &mut materials);
+                if *handle != material {
+                    *handle = material;
+                }
             }
         }
     }
 }
 
+//The closed door above a door tile, hidden while every switch holds a block
+#[derive(Component)]
+pub struct DoorPanel;
+
+//Turns a flat mark drawn pointing down (towards the camera) to point the given way
+fn facing_rotation(facing: Facing) -> Quat {
+    match facing {
+        Facing::Down => Quat::IDENTITY,
+        Facing::Up => Quat::from_rotation_y(PI),
+        Facing::Right => Quat::from_rotation_y(FRAC_PI_2),
+        Facing::Left => Quat::from_rotation_y(-FRAC_PI_2),
+    }
+}
+
 pub fn spawn_level(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     screen: impl Component + Clone,
-    grid: &mut ResMut<GameGrid>,
+    grid: &GameGrid,
 ){
     let mesh_handle = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
-    let material_handle0 = materials.add(Color::rgb(0.3, 0.5, 0.3).into());
-    let material_handle1 = materials.add(Color::rgb(0.7, 0.7, 0.3).into());
+    let floor_material = materials.add(Color::rgb(0.3, 0.5, 0.3).into());
+    let wall_material = materials.add(Color::rgb(0.7, 0.7, 0.3).into());
+    let ice_material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.7, 0.85, 0.95),
+        perceptual_roughness: 0.1,
+        ..default()
+    });
+    let conveyor_material = materials.add(Color::rgb(0.25, 0.25, 0.25).into());
+    //Arrows on conveyors and gates, a bar with a head pointing down before it is turned
+    let arrow_bar = meshes.add(Mesh::from(shape::Box::new(0.12, 0.02, 0.5)));
+    let arrow_head = meshes.add(Mesh::from(shape::Box::new(0.25, 0.02, 0.25)));
+    let arrow_material = materials.add(Color::WHITE.into());
+    let switch_pad = meshes.add(Mesh::from(shape::Box::new(0.6, 0.04, 0.6)));
+    let switch_material = materials.add(Color::rgb(0.8, 0.2, 0.2).into());
+    let door_mesh = meshes.add(Mesh::from(shape::Box::new(0.9, 0.9, 0.1)));
+    let door_material = materials.add(Color::rgb(0.45, 0.3, 0.2).into());
 
     for (k, v) in grid.grid.iter(){
-        if let Some(tile) = *v{
-            match tile {
-                GridCellType::Tile(id) => {
-                    match id {
-                        0 => {
-                            commands.spawn_bundle(PbrBundle {
-                                mesh: mesh_handle.clone(),
-                                material: material_handle0.clone(),
-                                transform: Transform::from_xyz(k.x as f32, k.y as f32, k.z as f32),
-                                ..default()
-                            })
-                            .insert(GridEntity{ cell_type: GridCellType::Tile(0)})
-                            .insert(screen.clone())
-                            .insert(CurrentPosition(GridCell::new(k.x, k.y, k.z)));
-                        },
-                        1 =>{
-                            commands.spawn_bundle(PbrBundle {
-                                mesh: mesh_handle.clone(),
-                                material: material_handle1.clone(),
-                                transform: Transform::from_xyz(k.x as f32, k.y as f32, k.z as f32),
-                                ..default()
-                            })
-                            .insert(GridEntity{ cell_type: GridCellType::Tile(1)})
-                            .insert(screen.clone())
-                            .insert(CurrentPosition(GridCell::new(k.x, k.y, k.z)));
-
-                        },
-                        _ => {},
-                    }
+        if let Some(GridCellType::Tile(id)) = *v{
+            let behavior = tile_behavior(id);
+            let material = match behavior {
+                TileBehavior::Wall => wall_material.clone(),
+                TileBehavior::Ice => ice_material.clone(),
+                TileBehavior::Conveyor(_) => conveyor_material.clone(),
+                _ => floor_material.clone(),
+            };
+            commands.spawn_bundle(PbrBundle {
+                mesh: mesh_handle.clone(),
+                material,
+                transform: Transform::from_xyz(k.x as f32, k.y as f32, k.z as f32),
+                ..default()
+            })
+            .insert(GridEntity{ cell_type: GridCellType::Tile(id)})
+            .insert(screen.clone())
+            .insert(CurrentPosition(GridCell::new(k.x, k.y, k.z)));
 
-                }
-                _ => {}
+            //Marks on top of the tile show what it does
+            let top = Vec3::new(k.x as f32, k.y as f32 + 0.51, k.z as f32);
+            match behavior {
+                TileBehavior::Conveyor(facing) | TileBehavior::Gate(facing) => {
+                    let rotation = facing_rotation(facing);
+                    commands.spawn_bundle(PbrBundle {
+                        mesh: arrow_bar.clone(),
+                        material: arrow_material.clone(),
+                        transform: Transform::from_translation(top + rotation * Vec3::new(0.0, 0.0, -0.1)).with_rotation(rotation),
+                        ..default()
+                    }).insert(screen.clone());
+                    commands.spawn_bundle(PbrBundle {
+                        mesh: arrow_head.clone(),
+                        material: arrow_material.clone(),
+                        transform: Transform::from_translation(top + rotation * Vec3::new(0.0, 0.0, 0.15))
+                            .with_rotation(rotation * Quat::from_rotation_y(FRAC_PI_4)),
+                        ..default()
+                    }).insert(screen.clone());
+                },
+                TileBehavior::Switch => {
+                    commands.spawn_bundle(PbrBundle {
+                        mesh: switch_pad.clone(),
+                        material: switch_material.clone(),
+                        transform: Transform::from_translation(top),
+                        ..default()
+                    }).insert(screen.clone());
+                },
+                TileBehavior::Door => {
+                    commands.spawn_bundle(PbrBundle {
+                        mesh: door_mesh.clone(),
+                        material: door_material.clone(),
+                        transform: Transform::from_xyz(k.x as f32, k.y as f32 + 1.0, k.z as f32),
+                        ..default()
+                    })
+                    .insert(DoorPanel)
+                    .insert(screen.clone());
+                },
+                _ => {},
             }
-
         }
     }
+}
 
-}
\ No newline at end of file
+//Doors show up closed until every switch holds a block
+pub fn show_doors(puzzle: Res<PuzzleState>, mut doors: Query<&mut Visibility, With<DoorPanel>>){
+    if !puzzle.is_changed() {
+        return;
+    }
+    let open = puzzle.doors_open();
+    for mut visibility in doors.iter_mut() {
+        visibility.is_visible = !open;
+    }
+}
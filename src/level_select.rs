@@ -0,0 +1,266 @@
+use bevy::prelude::*;
+use super::{despawn_screen, GameState};
+use super::levels::{CurrentLevel, LevelList};
+use super::save_game::SaveGame;
+use super::input::Action;
+
+const TILES_PER_ROW: usize = 5;
+//Imported collections can have hundreds of levels, only this many tiles are shown at once
+const TILES_PER_PAGE: usize = TILES_PER_ROW * 3;
+const LOCKED_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+const UNLOCKED_COLOR: Color = Color::rgb(0.53, 0.38, 0.2);
+const COMPLETED_COLOR: Color = Color::rgb(0.3, 0.5, 0.3);
+
+pub struct LevelSelectPlugin;
+
+//Helps to despawn entities
+#[derive(Component)]
+struct OnLevelSelectScreen;
+
+//A tile for the level with this index in the level list, the back button comes after the last level
+#[derive(Component)]
+pub struct LevelTile(usize);
+
+//The current selected tile
+pub struct SelectedTile(usize);
+
+//The text under the tiles telling which page is shown
+#[derive(Component)]
+struct PageText;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App){
+        app
+        // When entering the state, spawn everything needed for this screen
+        .add_system_set(SystemSet::on_enter(GameState::LevelSelect).with_system(setup))
+
+        // Run those systems on update for each frame
+        .add_system_set(SystemSet::on_update(GameState::LevelSelect)
+            .with_system(mark_selected)
+            .with_system(show_page)
+            .with_system(next_previous_button)
+            .with_system(select_button)
+            .with_system(mouse_on_button)
+            )
+
+        // When exiting the state, despawn everything that was spawned for this screen
+        .add_system_set(SystemSet::on_exit(GameState::LevelSelect)
+            .with_system(despawn_screen::<OnLevelSelectScreen>),);
+    }
+}
+
+// spawn everything needed for this screen
+fn setup(mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    levels: Res<LevelList>,
+    save: Res<SaveGame>,
+    mut actions: ResMut<Input<Action>>,
+){
+    //The confirm that opened this screen mustn't start a level right away
+    actions.clear();
+    commands.spawn_bundle(Camera2dBundle::default()).insert(OnLevelSelectScreen);
+
+    let font = asset_server.load("fonts/retganon.ttf");
+
+    //The tiles on top and the back button under them
+    let screen = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(OnLevelSelectScreen)
+        .id();
+
+    //Rows are filled from the top, wrap reverse because the ui y axis goes up
+    let grid = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px((244 * TILES_PER_ROW) as f32), Val::Auto),
+                flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::WrapReverse,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .id();
+    commands.entity(screen).add_child(grid);
+
+    for (index, level) in levels.0.iter().enumerate() {
+        let progress = save.levels.get(&level.id());
+        let completed = progress.is_some_and(|progress| progress.completed);
+        let (color, state) = if !levels.is_unlocked(index, &save) {
+            (LOCKED_COLOR, "Locked".to_string())
+        } else if completed {
+            let progress = progress.unwrap();
+            (COMPLETED_COLOR, format!("Best {} moves\n{} pushes", progress.best_moves.unwrap_or(0), progress.best_pushes.unwrap_or(0)))
+        } else {
+            (UNLOCKED_COLOR, "Not finished".to_string())
+        };
+        let number = match &level.collection {
+            Some(collection) => format!("{} {}", index + 1, collection),
+            None => (index + 1).to_string(),
+        };
+        let text = format!("{}\n{}\n{}", number, level.name, state);
+        let tile = spawn_tile(&mut commands, &font, &text, color, Size::new(Val::Px(220.0), Val::Px(150.0)), index);
+        commands.entity(grid).add_child(tile);
+    }
+
+    let page = commands.spawn_bundle(TextBundle::from_section(
+        "",
+        TextStyle {
+            font: font.clone(),
+            font_size: 26.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    )).insert(PageText).id();
+    commands.entity(screen).add_child(page);
+
+    let back = spawn_tile(&mut commands, &font, "BACK", UNLOCKED_COLOR, Size::new(Val::Px(192.0), Val::Px(48.0)), levels.0.len());
+    commands.entity(screen).add_child(back);
+
+    //Start on the level "Continue" would play
+    commands.insert_resource(SelectedTile(levels.continue_level(&save)));
+}
+
+fn spawn_tile(commands: &mut Commands, font: &Handle<Font>, text: &str, color: Color, size: Size<Val>, index: usize) -> Entity{
+    commands.spawn_bundle(ButtonBundle {
+        style: Style {
+            size,
+            margin: UiRect::all(Val::Px(12.)),
+            // center the text in the tile
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: color.into(),
+        ..default()
+    })
+    .insert(LevelTile(index))
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle::from_section(
+            text,
+            TextStyle {
+                font: font.clone(),
+                font_size: 26.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+            },
+        ).with_text_alignment(TextAlignment::CENTER));
+    }).id()
+}
+
+//Change the selected tile scale so you know with is it
+fn mark_selected(selected: Res<SelectedTile>, mut query: Query<(&mut Transform, &LevelTile)>){
+    for (mut transform, tile) in query.iter_mut(){
+        if selected.0 == tile.0{
+            transform.scale = Vec3::new(1.1, 1.1, 1.);
+        }
+        else{
+            transform.scale = Vec3::new(1., 1., 1.);
+        }
+    }
+}
+
+//Only the page of the selected tile is laid out, the back button keeps the page that was shown
+fn show_page(
+    selected: Res<SelectedTile>,
+    levels: Res<LevelList>,
+    mut tiles: Query<(&LevelTile, &mut Style)>,
+    mut page_text: Query<&mut Text, With<PageText>>,
+){
+    if !selected.is_changed() || selected.0 == levels.0.len() { return; }
+    let page = selected.0 / TILES_PER_PAGE;
+    for (tile, mut style) in tiles.iter_mut() {
+        if tile.0 == levels.0.len() { continue; }
+        style.display = if tile.0 / TILES_PER_PAGE == page { Display::Flex } else { Display::None };
+    }
+
+    let pages = (levels.0.len() + TILES_PER_PAGE - 1) / TILES_PER_PAGE;
+    for mut text in page_text.iter_mut() {
+        text.sections[0].value = if pages > 1 { format!("Page {} of {}", page + 1, pages) } else { String::new() };
+    }
+}
+
+//Left and right go through the tiles one by one, up and down a whole row, the back button is last
+fn next_previous_button(mut selected: ResMut<SelectedTile>, actions: Res<Input<Action>>, levels: Res<LevelList>){
+    let count = levels.0.len() + 1;
+    let mut selection = selected.0;
+
+    if actions.just_pressed(Action::MoveRight) {
+        selection = (selection + 1) % count;
+    }
+    if actions.just_pressed(Action::MoveLeft) {
+        selection = (selection + count - 1) % count;
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        selection = (selection + TILES_PER_ROW).min(count - 1);
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        selection = selection.saturating_sub(TILES_PER_ROW);
+    }
+    selected.0 = selection;
+}
+
+fn select_button(selected: Res<SelectedTile>,
+    actions: Res<Input<Action>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    save: Res<SaveGame>,
+    levels: Res<LevelList>,
+){
+    if actions.just_pressed(Action::Confirm){
+        accept_selection(selected.0, &mut game_state, &mut current_level, &save, &levels);
+    }
+    else if actions.just_pressed(Action::Back){
+        game_state.replace(GameState::Menu).unwrap();
+    }
+}
+
+fn mouse_on_button(
+    interaction_query: Query<
+        (&Interaction, &LevelTile),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut selected: ResMut<SelectedTile>,
+    mut game_state: ResMut<State<GameState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    save: Res<SaveGame>,
+    levels: Res<LevelList>,
+) {
+    for (interaction, tile) in interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => {
+                accept_selection(tile.0, &mut game_state, &mut current_level, &save, &levels);
+            },
+            Interaction::Hovered => {
+                selected.0 = tile.0;
+            },
+            Interaction::None => {},
+        }
+    }
+}
+
+//Locked levels can't be started, the tile after the levels is the back button
+fn accept_selection(
+    selected: usize,
+    game_state: &mut ResMut<State<GameState>>,
+    current_level: &mut ResMut<CurrentLevel>,
+    save: &Res<SaveGame>,
+    levels: &Res<LevelList>,
+){
+    if selected == levels.0.len() {
+        game_state.replace(GameState::Menu).unwrap();
+    }
+    else if levels.is_unlocked(selected, save) {
+        current_level.0 = selected;
+        game_state.replace(GameState::Game).unwrap();
+    }
+}
@@ -1,12 +1,264 @@
+use bevy::{prelude::*, asset::LoadState, gltf::Gltf};
+use crate::PlayerSpawned;
+use crate::grid_physics::*;
+use crate::player::{Player, spawn_player};
+use crate::level_description::{Level, load_levels, LEVELS_FOLDER};
+use crate::history::MoveHistory;
+use crate::puzzle::PuzzleState;
+use crate::save_game::SaveGame;
+use super::{GameLevel, GameState};
+use super::editor::{leave_play_test, PlayTest};
+use super::game::{LevelReset, WinTimer, GltfHandle};
+use super::despawn_screen;
+use super::level_entities::{show_doors, spawn_blocks, spawn_goal, spawn_level, tint_boxes, BoxMaterials};
+use super::ui::*;
+use super::hud::{hud_text, HudText};
+use super::input::Action;
 
-pub mod level1;
-pub mod level2;
-pub mod level3;
-pub mod level4;
-pub mod level5;
-pub mod level6;
-pub mod level7;
-pub mod level8;
-pub mod level9;
-pub mod level10;
-use super::*;
\ No newline at end of file
+
+#[derive(Component, Clone, Copy)]
+pub struct OnLevelScreen;
+
+//All the levels found in the levels folder, in the order they are played.
+//The game's own levels come first, the imported collections after them.
+pub struct LevelList(pub Vec<Level>);
+
+impl LevelList {
+    //Imported collections are open from the start, the game's own levels one after the other
+    pub fn is_unlocked(&self, index: usize, save: &SaveGame) -> bool {
+        index == 0 || self.0[index].collection.is_some() || save.is_unlocked(&self.0[index].id())
+    }
+
+    //Where the level with this id is in the list, if it is still there
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.0.iter().position(|level| level.id() == id)
+    }
+
+    //The level "Continue" starts, the first one when the saved one is gone
+    pub fn continue_level(&self, save: &SaveGame) -> usize {
+        save.continue_level.as_deref().and_then(|id| self.position(id)).unwrap_or(0)
+    }
+
+    //Next only goes on within the same collection, the game's own levels end with the credits
+    pub fn has_next(&self, index: usize) -> bool {
+        index + 1 < self.0.len() && self.0[index + 1].collection == self.0[index].collection
+    }
+}
+
+//Index in the level list of the level being played
+pub struct CurrentLevel(pub usize);
+
+//Seconds spent on the level being played, until it is solved
+pub struct LevelClock(pub f32);
+
+pub struct LevelSceneHandle {
+    pub handle: Handle<Gltf>,
+    pub is_loaded: bool,
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin{
+    fn build(&self, app: &mut App){
+        //The level files are read once, the menus need them before a game starts
+        //A broken file is skipped, the game only can't start without any level at all
+        let (mut levels, errors) = load_levels(LEVELS_FOLDER).unwrap_or_else(|error| panic!("Couldn't load the levels: {}", error));
+        for error in errors.iter() {
+            warn!("Skipping a level file that doesn't load: {}", error);
+        }
+        if levels.is_empty() {
+            panic!("None of the level files in {} could be loaded", LEVELS_FOLDER);
+        }
+        levels.sort_by_key(|level| level.collection.is_some());
+        app
+        .insert_resource(LevelList(levels))
+        .insert_resource(CurrentLevel(0))
+        .insert_resource(LevelClock(0.0))
+        .init_resource::<BoxMaterials>()
+        // When entering the state, wait for the scene and spawn the current level from the list
+        .add_system_set(SystemSet::on_enter(GameLevel::Playing).with_system(load))
+        .add_system_set(SystemSet::on_update(GameLevel::Playing).with_system(setup))
+        .add_system_set(SystemSet::on_update(GameLevel::Playing).with_system(reset))
+        .add_system_set(SystemSet::on_update(GameLevel::Playing).with_system(check_if_finished.after(setup)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(tick_clock))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(show_doors))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(tint_boxes))
+        .add_system_set(SystemSet::on_exit(GameLevel::Playing)
+            .with_system(despawn_screen::<OnLevelScreen>),);
+    }
+}
+
+fn load(mut commands: Commands, asset_server: Res<AssetServer>){
+    commands.insert_resource(LevelSceneHandle {
+        handle: asset_server.load("warrior_01.glb"),
+        is_loaded: false,
+    });
+
+}
+
+
+fn setup(
+    mut commands: Commands,
+    mut puzzle: ResMut<PuzzleState>,
+    mut pending: ResMut<PendingMoves>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets_gltf: Res<Assets<Gltf>>,
+    glft_handle: ResMut<GltfHandle>,
+    asset_server: Res<AssetServer>,
+    mut player_spawned: ResMut<State<PlayerSpawned>>,
+    mut scene_handle: ResMut<LevelSceneHandle>,
+    mut next_move: ResMut<BlockNextMove>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+    mut history: ResMut<MoveHistory>,
+    mut clock: ResMut<LevelClock>,
+    //Bevy systems take up to 16 parameters
+    (mut save, play_test): (ResMut<SaveGame>, Res<PlayTest>),
+){
+
+    if !scene_handle.is_loaded
+    && asset_server.get_load_state(&scene_handle.handle) == LoadState::Loaded{
+        scene_handle.is_loaded = true;
+        let level = &levels.0[current_level.0];
+
+        *puzzle = PuzzleState::from_level(level);
+        pending.0.clear();
+        history.clear();
+        clock.0 = 0.0;
+
+        //Quitting in the middle of this level continues from it, a play test isn't in the save
+        if play_test.0.is_none() && save.continue_level.as_deref() != Some(level.id().as_str()) {
+            save.continue_level = Some(level.id());
+            save.write();
+        }
+
+        spawn_level(&mut commands, &mut meshes, &mut materials, OnLevelScreen, &puzzle.grid);
+
+        // camera
+        let (x, y, z) = level.camera.position;
+        let (look_x, look_y, look_z) = level.camera.look_at;
+        commands.spawn_bundle(Camera3dBundle {
+            transform: Transform::from_xyz(x, y, z).looking_at(Vec3::new(look_x, look_y, look_z), Vec3::Y),
+            ..default()
+        }).insert(OnLevelScreen);
+        // light
+        commands.spawn_bundle(PointLightBundle {
+            point_light: PointLight {
+                intensity: 1500.0,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 8.0, 0.0),
+            ..default()
+        }).insert(OnLevelScreen);
+
+        //spawn reset button with the counters beside it
+        let hud = spawn_hud_container(&mut commands);
+        commands.entity(hud).insert(OnLevelScreen);
+        let button = spawn_button(&mut commands, &asset_server, "Reset", OnLevelScreen);
+        commands.entity(button).insert(ResetButton);
+        let text = spawn_text(&mut commands, &asset_server, &hud_text(&levels, &current_level, &puzzle), HudText);
+        commands.entity(hud).push_children(&[button, text]);
+
+        spawn_player(&mut commands, &assets_gltf, &glft_handle, level.player, OnLevelScreen);
+
+        //The boxes and the goals come from the puzzle, the same one that tells when the level is solved
+        for (id, block_position) in puzzle.blocks.iter().enumerate(){
+            spawn_blocks(&mut commands, &glft_handle, *block_position, OnLevelScreen, id as i32);
+        }
+
+        for goal in puzzle.goals.iter(){
+            spawn_goal(&mut commands, &mut meshes, &mut materials, *goal, OnLevelScreen);
+        }
+
+        next_move.0 = false;
+        player_spawned.replace(PlayerSpawned::Yes).unwrap();
+    }
+}
+
+//The reset button or the reset action load the level again
+pub fn reset(
+    mut level_reset: ResMut<LevelReset>,
+    mut level_state: ResMut<State<GameLevel>>,
+    mut player_spawned: ResMut<State<PlayerSpawned>>,
+    mut interaction_query: Query<
+    (&Interaction,&mut Transform),
+    (Changed<Interaction>, With<ResetButton>, Without<ToMove>, Without<Player>)>,
+    actions: Res<Input<Action>>,
+){
+    let mut clicked = false;
+    for (interaction, mut button_transform)  in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                clicked = true;
+            },
+            Interaction::Hovered => {
+                button_transform.scale = Vec3::new(1.2, 1.2, 1.);
+            },
+            Interaction::None => {
+                button_transform.scale = Vec3::new(1., 1., 1.);
+            },
+        }
+    }
+
+    //The level is still loading until the player is spawned
+    if player_spawned.current() != &PlayerSpawned::Yes { return; }
+    if clicked || actions.just_pressed(Action::Reset) {
+        player_spawned.replace(PlayerSpawned::No).expect("Player pop state wasn't successful");
+        level_reset.reset = true;
+        level_reset.level = GameLevel::Playing;
+        level_state.replace(GameLevel::None).expect("The game level state wasn't setted successfully");
+    }
+}
+
+//Opens the level complete screen once the last push is animated, the level stays behind it
+fn check_if_finished(
+    puzzle: Res<PuzzleState>,
+    pending: Res<PendingMoves>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+    mut player_spawned: ResMut<State<PlayerSpawned>>,
+    mut block: ResMut<BlockNextMove>,
+    mut level: ResMut<State<GameLevel>>,
+    mut timer: ResMut<WinTimer>,
+    time: Res<Time>,
+    mut save: ResMut<SaveGame>,
+    clock: Res<LevelClock>,
+    play_test: Res<PlayTest>,
+    mut game_state: ResMut<State<GameState>>,
+){
+    if player_spawned.current() != &PlayerSpawned::Yes { return;}
+
+    if puzzle.is_solved() && pending.0.is_empty(){
+        block.0 = true;
+        timer.0.tick(time.delta());
+        if timer.0.finished(){
+            timer.0.reset();
+            //A play tested level goes straight back to the editor
+            if play_test.0.is_some() {
+                leave_play_test(&mut game_state, &mut level, &mut player_spawned);
+                return;
+            }
+            let finished = &levels.0[current_level.0];
+            save.complete(&finished.id(), puzzle.moves, puzzle.pushes, clock.0);
+            //Continue goes on with the next level, the last one has the credits after it
+            if levels.has_next(current_level.0) {
+                let next = levels.0[current_level.0 + 1].id();
+                if finished.collection.is_none() {
+                    save.unlock(&next);
+                }
+                save.continue_level = Some(next);
+            }
+            save.write();
+            level.push(GameLevel::Complete).expect("Something went wrong showing the level complete screen");
+            player_spawned.push(PlayerSpawned::Paused).expect("Something went wrong setting player state to paused");
+        }
+    }
+}
+
+fn tick_clock(mut clock: ResMut<LevelClock>, puzzle: Res<PuzzleState>, time: Res<Time>){
+    if !puzzle.is_solved() {
+        clock.0 += time.delta_seconds();
+    }
+}
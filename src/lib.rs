@@ -0,0 +1,14 @@
+// The puzzle rules and the level files, without anything that needs a window.
+// The game drives these and tools can use the exact same rules headless.
+pub mod game_grid;
+pub mod tiles;
+pub mod goals;
+pub mod level_description;
+pub mod tiled_map;
+pub mod xsb;
+pub mod editing;
+pub mod puzzle;
+pub mod deadlock;
+pub mod solver;
+pub mod validation;
+pub mod replay;
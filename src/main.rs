@@ -4,30 +4,42 @@ use splash::SplashPlugin;
 mod ui;
 mod menu;
 use menu::MenuPlugin;
+mod level_select;
+use level_select::LevelSelectPlugin;
+mod input;
+use input::ActionPlugin;
+mod controls;
+use controls::ControlsPlugin;
+mod editor;
+use editor::EditorPlugin;
 mod pause;
 use pause::GamePausePlugin;
+mod level_complete;
+use level_complete::LevelCompletePlugin;
+mod credits;
+use credits::CreditsPlugin;
 mod game;
 use game::GamePlugin;
-mod game_grid;
+use carrier_clone::{deadlock, editing, game_grid, goals, level_description, puzzle, replay, solver, tiles, validation};
 mod grid_physics;
 use grid_physics::GridPhysicsPlugin;
 mod player;
 use player::PlayerPlugin;
+mod history;
+use history::HistoryPlugin;
+mod hint;
+use hint::HintPlugin;
+mod deadlock_warning;
+use deadlock_warning::DeadlockWarningPlugin;
+mod recording;
+use recording::RecordingPlugin;
+mod hud;
+use hud::HudPlugin;
 mod levels;
-use levels::{
-    level1::LevelOnePlugin,
-    level2::LevelTwoPlugin,
-    level3::LevelThreePlugin,
-    level4::LevelFourPlugin,
-    level5::LevelFivePlugin,
-    level6::LevelSixPlugin,
-    level7::LevelSevenPlugin,
-    level8::LevelEightPlugin,
-    level9::LevelNinePlugin,
-    level10::LevelTenPlugin,
- };
+use levels::LevelPlugin;
 mod level_entities;
-use level_entities::{spawn_blocks,};
+mod save_game;
+use save_game::SaveGamePlugin;
 
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -35,6 +47,9 @@ pub enum GameState {
     Splash,
     Load,
     Menu,
+    LevelSelect,
+    Controls,
+    Editor,
     Game,
     Credits,
 }
@@ -43,16 +58,8 @@ pub enum GameState {
 pub enum GameLevel{
     None,
     Pause,
-    LevelOne,
-    LevelTwo,
-    LevelThree,
-    LevelFour,
-    LevelFive,
-    LevelSix,
-    LevelSeven,
-    LevelEight,
-    LevelNine,
-    LevelTen,
+    Playing,
+    Complete,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -83,20 +90,23 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(SplashPlugin)
         .add_plugin(MenuPlugin)
+        .add_plugin(LevelSelectPlugin)
+        .add_plugin(ActionPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(EditorPlugin)
         .add_plugin(GamePausePlugin)
+        .add_plugin(LevelCompletePlugin)
         .add_plugin(GamePlugin)
+        .add_plugin(CreditsPlugin)
         .add_plugin(GridPhysicsPlugin)
-        .add_plugin(LevelOnePlugin)
-        .add_plugin(LevelTwoPlugin)
-        .add_plugin(LevelThreePlugin)
-        .add_plugin(LevelFourPlugin)
-        .add_plugin(LevelFivePlugin)
-        .add_plugin(LevelSixPlugin)
-        .add_plugin(LevelSevenPlugin)
-        .add_plugin(LevelEightPlugin)
-        .add_plugin(LevelNinePlugin)
-        .add_plugin(LevelTenPlugin)
+        .add_plugin(LevelPlugin)
         .add_plugin(PlayerPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(HintPlugin)
+        .add_plugin(DeadlockWarningPlugin)
+        .add_plugin(RecordingPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(SaveGamePlugin)
         .add_state(GameState::Splash)
         .add_state(PlayerSpawned::No)
         .add_state(GameLevel::None)
@@ -2,13 +2,20 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use bevy::{prelude::*, app::AppExit,};
 use super::{despawn_screen, GameState, ui::* };
+use super::levels::{CurrentLevel, LevelList};
+use super::save_game::SaveGame;
+use super::input::Action;
 
 
 pub struct MenuPlugin;
 //Enum values representing all the menu buttons
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, FromPrimitive)]
 pub enum MenuButtonType{
+    Continue,
     Start,
+    Levels,
+    Controls,
+    Editor,
     Quit,
 }
 
@@ -42,12 +49,12 @@ impl Plugin for MenuPlugin {
 }
 
 // spawn everything needed for this screen
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>){
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, save: Res<SaveGame>){
     
     commands.spawn_bundle(Camera2dBundle::default()).insert(OnMenuScreen);
 
     //All the button names
-    let button_texts = vec!["START", "QUIT"];
+    let button_texts = vec!["CONTINUE", "START", "LEVELS", "CONTROLS", "EDITOR", "QUIT"];
 
     //A container entity containing all the buttons
     let node = spawn_menu_container(&mut commands);
@@ -62,16 +69,29 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>){
         
         commands.entity(node).add_child(button);
     }
-    //Insert the resource with default value
-    commands.insert_resource(SelectedButton(MenuButtonType::Start));
+    //Insert the resource with default value, continue when there is something to continue
+    let selected = if save.has_progress() { MenuButtonType::Continue } else { MenuButtonType::Start };
+    commands.insert_resource(SelectedButton(selected));
 }
 
 //Helper function gets the enum value from text
 fn menu_item_from_text(text: &str) -> MenuButton {
     match text {
+        "CONTINUE" => {
+            MenuButton(MenuButtonType::Continue)
+        },
         "START" => {
             MenuButton(MenuButtonType::Start)
         },
+        "LEVELS" => {
+            MenuButton(MenuButtonType::Levels)
+        },
+        "CONTROLS" => {
+            MenuButton(MenuButtonType::Controls)
+        },
+        "EDITOR" => {
+            MenuButton(MenuButtonType::Editor)
+        },
         "QUIT" => {
             MenuButton(MenuButtonType::Quit)
         },
@@ -95,29 +115,32 @@ fn mark_selected(selected: Res<SelectedButton>, mut query: Query<(&mut Transform
 }
 
 //Move between button values with the arrow keys (or what ever is the up/down keys) 
-fn next_previous_button(mut selected: ResMut<SelectedButton>,keyboard_input: Res<Input<KeyCode>>){
+fn next_previous_button(mut selected: ResMut<SelectedButton>,actions: Res<Input<Action>>){
     
     let mut int_selection= selected.0 as u8;
 
-    if keyboard_input.just_pressed(KeyCode::Down) {
-        //int_selection = (int_selection + 1) % 5;
-        int_selection = if int_selection < 1 { int_selection + 1 }else{ 0 };
+    if actions.just_pressed(Action::MoveDown) {
+        //int_selection = (int_selection + 1) % 6;
+        int_selection = if int_selection < 5 { int_selection + 1 }else{ 0 };
     }
 
-    if keyboard_input.just_pressed(KeyCode::Up) {
-        //int_selection = (int_selection - 1) % 5;
-        int_selection = if int_selection > 0 { int_selection - 1 }else{ 1 };
+    if actions.just_pressed(Action::MoveUp) {
+        //int_selection = (int_selection - 1) % 6;
+        int_selection = if int_selection > 0 { int_selection - 1 }else{ 5 };
     }
     selected.0 = MenuButtonType::from_u8(int_selection).unwrap();
 }
 
 fn select_button(selected: ResMut<SelectedButton>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Input<Action>>,
     mut game_state: ResMut<State<GameState>>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut current_level: ResMut<CurrentLevel>,
+    save: Res<SaveGame>,
+    levels: Res<LevelList>,
 ){
-    if keyboard_input.just_pressed(KeyCode::Return){
-        accept_selection(&selected, &mut game_state,  &mut app_exit_events);
+    if actions.just_pressed(Action::Confirm){
+        accept_selection(&selected, &mut game_state,  &mut app_exit_events, &mut current_level, &save, &levels);
 
     }
 }
@@ -130,11 +153,14 @@ fn mouse_on_button(
     mut selected: ResMut<SelectedButton>,
     mut game_state: ResMut<State<GameState>>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut current_level: ResMut<CurrentLevel>,
+    save: Res<SaveGame>,
+    levels: Res<LevelList>,
 ) {
     for (interaction, menu_button)  in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                accept_selection(&selected, &mut game_state, &mut app_exit_events);
+                accept_selection(&selected, &mut game_state, &mut app_exit_events, &mut current_level, &save, &levels);
             },
             Interaction::Hovered => {
                 selected.0 = menu_button.0;
@@ -149,11 +175,28 @@ fn accept_selection(
     selected: &ResMut<SelectedButton>,
     game_state: &mut ResMut<State<GameState>>,
     app_exit_events: &mut EventWriter<AppExit>,
+    current_level: &mut ResMut<CurrentLevel>,
+    save: &Res<SaveGame>,
+    levels: &Res<LevelList>,
 ){
     match selected.0 {
+        MenuButtonType::Continue => {
+            current_level.0 = levels.continue_level(save);
+            game_state.replace(GameState::Game).unwrap();
+        },
         MenuButtonType::Start => {
+            current_level.0 = 0;
             game_state.replace(GameState::Game).unwrap();
         },
+        MenuButtonType::Levels => {
+            game_state.replace(GameState::LevelSelect).unwrap();
+        },
+        MenuButtonType::Controls => {
+            game_state.replace(GameState::Controls).unwrap();
+        },
+        MenuButtonType::Editor => {
+            game_state.replace(GameState::Editor).unwrap();
+        },
         MenuButtonType::Quit => app_exit_events.send(AppExit),
         _ => {}
     }
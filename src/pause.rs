@@ -2,6 +2,7 @@ use bevy::{prelude::*, app::AppExit, render::view::visibility};
 use num_derive::FromPrimitive;    
 use num_traits::FromPrimitive;
 use super::{despawn_screen, GameLevel, GameState, PlayerSpawned, ui::*};
+use super::input::Action;
 
 pub struct GamePausePlugin;
 
@@ -82,14 +83,16 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut button_quer
     commands.entity(shadow).add_child(node);
 }
 
-fn pause_pressed(mut keyboard_input: ResMut<Input<KeyCode>>,
+fn pause_pressed(mut actions: ResMut<Input<Action>>,
     mut game_state: ResMut<State<GameLevel>>,
     mut player_spawned: ResMut<State<PlayerSpawned>>
 ){
-    if keyboard_input.just_pressed(KeyCode::Escape){
+    //Only a level being played can be paused, not the screens on top of it
+    if game_state.current() != &GameLevel::Playing { return; }
+    if actions.just_pressed(Action::Pause){
         game_state.push(GameLevel::Pause).unwrap();
         player_spawned.push(PlayerSpawned::Paused).unwrap();
-        keyboard_input.clear();
+        actions.clear();
     }
 }
 
@@ -121,15 +124,15 @@ fn mark_selected(selected: Res<SelectedPausebutton>, mut query: Query<(&mut Tran
 }
 
 //Move between button values with the arrow keys (or what ever is the up/down keys) 
-fn next_previous_button(mut selected: ResMut<SelectedPausebutton>,keyboard_input: Res<Input<KeyCode>>){
+fn next_previous_button(mut selected: ResMut<SelectedPausebutton>,actions: Res<Input<Action>>){
     let mut int_selection= selected.0 as u8;
 
-    if keyboard_input.just_pressed(KeyCode::Down) {
+    if actions.just_pressed(Action::MoveDown) {
         //int_selection = (int_selection + 1) % 5;
         int_selection = if int_selection < 1 { int_selection + 1 }else{ 0 };
     }
 
-    if keyboard_input.just_pressed(KeyCode::Up) {
+    if actions.just_pressed(Action::MoveUp) {
         //int_selection = (int_selection - 1) % 5;
         int_selection = if int_selection > 0 { int_selection - 1 }else{ 1 };
     }
@@ -137,21 +140,21 @@ fn next_previous_button(mut selected: ResMut<SelectedPausebutton>,keyboard_input
 }
 
 fn select_button(selected: ResMut<SelectedPausebutton>,
-    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut actions: ResMut<Input<Action>>,
     mut level_state: ResMut<State<GameLevel>>,
     mut app_exit_events: EventWriter<AppExit>,
     mut player_spawn_state: ResMut<State<PlayerSpawned>>,
 
 ){
-    if keyboard_input.just_pressed(KeyCode::Return){
+    if actions.just_pressed(Action::Confirm){
         accept_selection(&selected, &mut level_state, &mut player_spawn_state, &mut app_exit_events);
 
     }
-    else if keyboard_input.just_pressed(KeyCode::Escape)
+    else if actions.just_pressed(Action::Pause) || actions.just_pressed(Action::Back)
     {
         level_state.pop().unwrap();
         player_spawn_state.pop().unwrap();
-        keyboard_input.clear();
+        actions.clear();
     }
 }
 
@@ -1,18 +1,14 @@
 use num_derive::FromPrimitive;    
 use num_traits::FromPrimitive;
+use std::collections::VecDeque;
 use bevy::{prelude::*, gltf::Gltf};
 use super::{grid_physics::*, PlayerSpawned, despawn_screen, game_grid::*,};
 use std::f32::consts::TAU;
 use super::game::{PlayerRotationTimer, GltfHandle};
-
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, FromPrimitive)]
-pub enum Facing {
-    Up,
-    Down,
-    Right,
-    Left,
-    
-}
+use super::history::MoveHistory;
+use super::hint::SolutionPlayback;
+use super::input::{Action, ActionRepeat};
+use super::puzzle::{Facing, Outcome, PlayerMove, PuzzleState};
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, FromPrimitive)]
 pub enum PlayerState{
@@ -29,17 +25,29 @@ pub struct Player{
 
 pub struct PlayerPlugin;
 
+//Moves pressed while the previous one is still animated, played as soon as it ends
+const QUEUED_MOVES: usize = 2;
+
+//Every move keeps its action, a repeated one is dropped if the key was let go before it is played
+#[derive(Default)]
+pub struct MoveQueue(pub VecDeque<(Action, Facing, bool)>);
+
 #[derive(Component)]
 pub struct PlayerRotation(pub bool);
 
 impl Plugin for PlayerPlugin{
     fn build(&self, app: &mut App){
         app
-        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(player_input.after(update_grid)))
-        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(move_player.after(player_input)))
+        .init_resource::<MoveQueue>()
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(queue_moves.before(player_input)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(player_input.before(start_moves)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(move_player.after(start_moves)))
         .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(rotate_player))
 //        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(animate_player))
-        .add_system_set(SystemSet::on_exit(PlayerSpawned::Yes).with_system(despawn_screen::<Player>));
+        .add_system_set(SystemSet::on_exit(PlayerSpawned::Yes)
+            .with_system(despawn_screen::<Player>)
+            .with_system(clear_queue))
+        .add_system_set(SystemSet::on_pause(PlayerSpawned::Yes).with_system(clear_queue));
         
     }
 }
@@ -82,98 +90,68 @@ pub fn spawn_player(commands: &mut Commands,
 
 }
 
+//Keeps the pressed moves until the player is free to make them
+fn queue_moves(actions: Res<Input<Action>>, repeat: Res<ActionRepeat>, mut queue: ResMut<MoveQueue>){
+    let moves = [
+        (Action::MoveLeft, Facing::Left),
+        (Action::MoveRight, Facing::Right),
+        (Action::MoveUp, Facing::Up),
+        (Action::MoveDown, Facing::Down),
+    ];
+    for (action, facing) in moves {
+        if !actions.just_pressed(action) { continue; }
+        let repeated = repeat.repeated(action);
+        //Holding a key only keeps one move waiting, so the player stops where the key is let go
+        if repeated && !queue.0.is_empty() { continue; }
+        if queue.0.len() < QUEUED_MOVES {
+            queue.0.push_back((action, facing, repeated));
+        }
+    }
+}
+
+fn clear_queue(mut queue: ResMut<MoveQueue>){
+    queue.0.clear();
+}
+
 fn player_input(
-    mut player_query: Query<(&mut CurrentPosition, &mut GridVelocity, &mut ToMove, &mut PlayerRotation, &mut Player), With<Player>>,
-    mut tile_query: Query<(&mut CurrentPosition, &mut GridVelocity, &mut ToMove, &GridEntity), Without<Player>>,
-    input: Res<Input<KeyCode>>, 
-    game_grid: Res<GameGrid>,
-    mut timer: ResMut<MoveTimer>,
+    mut player_query: Query<(&ToMove, &mut Player), With<Player>>,
+    actions: Res<Input<Action>>,
+    mut queue: ResMut<MoveQueue>,
+    mut puzzle: ResMut<PuzzleState>,
+    mut pending: ResMut<PendingMoves>,
     block: Res<BlockNextMove>,
-    mut rotation_timer: ResMut<PlayerRotationTimer>
+    mut rotation_timer: ResMut<PlayerRotationTimer>,
+    mut history: ResMut<MoveHistory>,
+    playback: Res<SolutionPlayback>,
 
 ){
-    if block.0 { return;}
-    for (player_position, mut player_velocity, mut move_player, mut player_rotation, mut player) in player_query.iter_mut(){
+    //Moves made before the level was solved or the solution started playing are forgotten
+    if puzzle.is_solved() || !playback.0.is_empty() {
+        queue.0.clear();
+        return;
+    }
+    if block.0 || !pending.0.is_empty() { return;}
+    for (move_player, mut player) in player_query.iter_mut(){
 
         if move_player.0 {return;}
-        let mut check_input = false;
-        if input.just_pressed(KeyCode::Left){
-            player_velocity.0.x = -1;
-            check_input = true;
-            
-            rotation_timer.0.reset();
-                player.target_facing = Facing::Left;
-            
-        }
-        else if input.just_pressed(KeyCode::Right){
-            player_velocity.0.x = 1;
-            check_input = true;
-           
-            rotation_timer.0.reset();
-                player.target_facing = Facing::Right;
-            
-        }
-        else if input.just_pressed(KeyCode::Up){
-            player_velocity.0.z = -1;
-            check_input = true;
-            
-            rotation_timer.0.reset();
-                player.target_facing = Facing::Up;
-        }
-        else if input.just_pressed(KeyCode::Down){
-            player_velocity.0.z = 1;
-            check_input = true;
-            
-            rotation_timer.0.reset();
-                player.target_facing = Facing::Down;
-            
-        }
-        
 
-        player_rotation.0 =  input.just_pressed(KeyCode::R);
+        let facing = loop {
+            match queue.0.pop_front() {
+                Some((action, _, true)) if !actions.pressed(action) => continue,
+                Some((_, facing, _)) => break facing,
+                None => return,
+            }
+        };
+
+        rotation_timer.0.reset();
+        player.target_facing = facing;
 
-        if !check_input {return;}
-        
-        let target_position = player_position.0 + player_velocity.0;
-        let player_target_cell = game_grid.grid.get(&target_position).unwrap();
-        match player_target_cell {
-            None => {
-                let one_cell_below = target_position + GridCell::new(0,-1,0);
-                let ground_gap = game_grid.grid.get(&one_cell_below).unwrap();
-                if ground_gap.is_none() {
-                    player_velocity.0.reset();
-                    return;
-                };
-                move_player.0 = true;
-                timer.0.reset();
-            },
-            Some(cell) => {
-                match cell {
-                    GridCellType::MovingTile(id) =>{
-                        for (tile_position,
-                            mut tile_velocity,
-                            mut move_tile,
-                            grid_entity ) 
-                            in tile_query.iter_mut()
-                        {
-                            if grid_entity.cell_type != GridCellType::MovingTile(id.clone()) { continue; } 
-
-                            let tile_target_position = tile_position.0 + player_velocity.0;
-                            let tile_target_cell = game_grid.grid.get(&tile_target_position).unwrap();
-                            if tile_target_cell.is_none(){
-                                move_player.0 = true;
-                                move_tile.0 = true;
-                                tile_velocity.0 = player_velocity.0;
-                                timer.0.reset();
-                            }else{
-                                player_velocity.0.reset();
-                            }
-                        }
-                    },
-                    GridCellType::Tile(_) => player_velocity.0.reset(),
-                    _ => {}
-                }
-            }, 
+        //The puzzle applies the whole step at once, the entities follow it through the pending moves.
+        //The pull key counts when the step is made, it can be pressed after the move was queued.
+        let before = puzzle.clone();
+        if let Outcome::Moved(step) = puzzle.play(PlayerMove{ facing, pull: actions.pressed(Action::Pull) }) {
+            history.record(before, &step);
+            pending.push_step(&step);
         }
     }
 }
@@ -183,7 +161,6 @@ fn move_player(
     mut query: Query<(&mut GridVelocity, &mut ToMove, &mut Transform, &mut CurrentPosition), With<Player>>,
     time: Res<Time>,
     mut timer: ResMut<MoveTimer>,
-    mut game_grid: ResMut<GameGrid>
 ){
     timer.tick(time.delta());
     for (mut velocity, mut to_move, mut transform, mut position) in query.iter_mut() {
@@ -192,8 +169,6 @@ fn move_player(
             transform.translation = position.0.as_vec3() + velocity.0.as_vec3() * timer.percent();
             if timer.finished() {
                 to_move.0 = false;
-                let value = game_grid.grid.get_mut(&position.0).unwrap();
-                *value = None;
                 position.0 = position.0 + velocity.0;
                 velocity.0.reset();
             }
@@ -0,0 +1,716 @@
+use num_derive::FromPrimitive;
+use super::game_grid::{GameGrid, GridCell, GridCellType};
+use super::goals::{BlockColor, BlockKind, Goal};
+use super::level_description::{Level, LevelRules};
+use super::tiles::{tile_behavior, TileBehavior};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, FromPrimitive)]
+pub enum Facing {
+    Up,
+    Down,
+    Right,
+    Left,
+
+}
+
+impl Facing {
+    //The cell offset of one step in this direction
+    pub fn offset(&self) -> GridCell {
+        match self {
+            Facing::Up => GridCell::new(0, 0, -1),
+            Facing::Down => GridCell::new(0, 0, 1),
+            Facing::Right => GridCell::new(1, 0, 0),
+            Facing::Left => GridCell::new(-1, 0, 0),
+        }
+    }
+}
+
+//A step as the player asks for it, pulling the block behind when the level allows it
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct PlayerMove {
+    pub facing: Facing,
+    pub pull: bool,
+}
+
+//One thing on the grid going from one cell to another
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct CellMove {
+    pub mover: GridCellType,
+    pub from: GridCell,
+    pub to: GridCell,
+}
+
+//Everything that changed in a single step
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Step {
+    pub facing: Facing,
+    //The player move and the blocks it pushed, nearest first, they happen at the same time
+    pub moves: Vec<CellMove>,
+    //What moves on by itself after that, one group of cell moves after the other: pushed blocks sliding
+    //and falling into holes, the player sliding over ice and the conveyors carrying what stands on them
+    pub after: Vec<Vec<CellMove>>,
+    pub pushed: bool,
+    pub pulled: bool,
+    pub solved: bool,
+}
+
+//Where a broken block is kept, below every grid. A move to it is the block breaking.
+pub const BROKEN: GridCell = GridCell { x: -1, y: -1, z: -1 };
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Outcome {
+    //Nothing changed, the way is blocked
+    Blocked,
+    Moved(Step),
+}
+
+//The rules of the puzzle without anything of bevy, so tools and solvers play with the same rules as the game.
+//The grid holds the player and the blocks, block ids are indexes in `blocks`.
+#[derive(Clone)]
+pub struct PuzzleState {
+    pub grid: GameGrid,
+    pub player: GridCell,
+    pub blocks: Vec<GridCell>,
+    //The color and the kind of every block, by block id
+    pub colors: Vec<Option<BlockColor>>,
+    pub kinds: Vec<BlockKind>,
+    pub goals: Vec<Goal>,
+    //Steps and pushes made since the level started, undo takes them back with the rest of the state
+    pub moves: usize,
+    pub pushes: usize,
+    pub rules: LevelRules,
+    //The cells on top of the switch and the conveyor tiles, found once from the grid
+    pub switches: Vec<GridCell>,
+    pub conveyors: Vec<(GridCell, Facing)>,
+}
+
+impl PuzzleState {
+    pub fn new(mut grid: GameGrid, player: GridCell, blocks: Vec<GridCell>, goals: Vec<Goal>) -> Self {
+        grid.grid.insert(player, Some(GridCellType::Player));
+        for (id, block) in blocks.iter().enumerate() {
+            grid.grid.insert(*block, Some(GridCellType::MovingTile(id as i32)));
+        }
+        let mut switches = Vec::new();
+        let mut conveyors = Vec::new();
+        for (cell, tile) in grid.grid.iter() {
+            if let Some(GridCellType::Tile(id)) = tile {
+                let on_top = *cell + GridCell::new(0, 1, 0);
+                match tile_behavior(*id) {
+                    TileBehavior::Switch => switches.push(on_top),
+                    TileBehavior::Conveyor(way) => conveyors.push((on_top, way)),
+                    _ => {},
+                }
+            }
+        }
+        //The grid is a hash map, sorted the conveyors always move things in the same order
+        switches.sort_by_key(|cell| (cell.y, cell.z, cell.x));
+        conveyors.sort_by_key(|(cell, _)| (cell.y, cell.z, cell.x));
+        let colors = vec![None; blocks.len()];
+        let kinds = vec![BlockKind::Plain; blocks.len()];
+        PuzzleState { grid, player, blocks, colors, kinds, goals, moves: 0, pushes: 0, rules: LevelRules::default(), switches, conveyors }
+    }
+
+    pub fn from_level(level: &Level) -> Self {
+        let blocks = level.blocks.iter().map(|block| block.cell).collect();
+        let mut puzzle = PuzzleState::new(level.grid.clone(), level.player, blocks, level.goals.clone());
+        puzzle.colors = level.blocks.iter().map(|block| block.color).collect();
+        puzzle.kinds = level.blocks.iter().map(|block| block.kind).collect();
+        puzzle.rules = level.rules;
+        puzzle
+    }
+
+    //None when the cell is outside the grid
+    pub fn cell(&self, cell: GridCell) -> Option<Option<GridCellType>> {
+        self.grid.grid.get(&cell).copied()
+    }
+
+    pub fn is_empty(&self, cell: GridCell) -> bool {
+        self.cell(cell) == Some(None)
+    }
+
+    //Something to stand on
+    pub fn has_ground(&self, cell: GridCell) -> bool {
+        matches!(self.cell(cell + GridCell::new(0, -1, 0)), Some(Some(_)))
+    }
+
+    //A cell the player could stand on if nothing was on it
+    pub fn is_floor(&self, cell: GridCell) -> bool {
+        matches!(self.cell(cell), Some(None) | Some(Some(GridCellType::Player)) | Some(Some(GridCellType::MovingTile(_))))
+            && self.has_ground(cell)
+    }
+
+    //An empty cell with something to stand on
+    pub fn is_walkable(&self, cell: GridCell) -> bool {
+        self.is_empty(cell) && self.has_ground(cell)
+    }
+
+    //What the tile under the cell does, none when there is no tile under it
+    pub fn ground_behavior(&self, cell: GridCell) -> Option<TileBehavior> {
+        match self.cell(cell + GridCell::new(0, -1, 0)) {
+            Some(Some(GridCellType::Tile(id))) => Some(tile_behavior(id)),
+            _ => None,
+        }
+    }
+
+    fn on_ice(&self, cell: GridCell) -> bool {
+        self.ground_behavior(cell) == Some(TileBehavior::Ice)
+    }
+
+    //The doors are open while there is a block on every switch of the level
+    pub fn doors_open(&self) -> bool {
+        !self.switches.is_empty()
+            && self.switches.iter().all(|cell| matches!(self.cell(*cell), Some(Some(GridCellType::MovingTile(_)))))
+    }
+
+    fn door_closed(&self, cell: GridCell) -> bool {
+        self.ground_behavior(cell) == Some(TileBehavior::Door) && !self.doors_open()
+    }
+
+    //Gates only let things in going their way and closed doors nothing at all
+    pub fn can_enter(&self, cell: GridCell, facing: Facing) -> bool {
+        match self.ground_behavior(cell) {
+            Some(TileBehavior::Gate(way)) => way == facing,
+            _ => !self.door_closed(cell),
+        }
+    }
+
+    //Puts the player and the blocks on new cells without going through the rules
+    pub fn place(&mut self, player: GridCell, blocks: &[GridCell]) {
+        self.grid.grid.insert(self.player, None);
+        for block in self.blocks.iter().filter(|block| **block != BROKEN) {
+            self.grid.grid.insert(*block, None);
+        }
+        self.player = player;
+        self.blocks = blocks.to_vec();
+        self.grid.grid.insert(player, Some(GridCellType::Player));
+        for (id, block) in self.blocks.iter().enumerate().filter(|(_, block)| **block != BROKEN) {
+            self.grid.grid.insert(*block, Some(GridCellType::MovingTile(id as i32)));
+        }
+    }
+
+    //A block the goal takes stands on it
+    pub fn is_goal_filled(&self, goal: &Goal) -> bool {
+        match self.cell(goal.cell) {
+            Some(Some(GridCellType::MovingTile(id))) => goal.accepts(self.colors[id as usize]),
+            _ => false,
+        }
+    }
+
+    //Every goal has a block of its color on it
+    pub fn is_solved(&self) -> bool {
+        self.goals.iter().all(|goal| self.is_goal_filled(goal))
+    }
+
+    pub fn goal_cells(&self) -> Vec<GridCell> {
+        self.goals.iter().map(|goal| goal.cell).collect()
+    }
+
+    //A step or a pull, as the player asked for it
+    pub fn play(&mut self, player_move: PlayerMove) -> Outcome {
+        if player_move.pull { self.pull(player_move.facing) } else { self.step(player_move.facing) }
+    }
+
+    //Moves the player one cell, pushing the block in front of it, or the row of blocks when the level allows
+    //chain pushes, sliding them on when it has ice and letting the blocks fall into holes.
+    //When the blocks can't be pushed the player climbs on the first one instead, like on a ledge.
+    pub fn step(&mut self, facing: Facing) -> Outcome {
+        let offset = facing.offset();
+        let target = self.player + offset;
+        let mut moves = Vec::new();
+        let mut pushed = Vec::new();
+
+        let to = match self.pushed_row(target, facing) {
+            Some(row) => {
+                //The front block goes first so every block moves into an empty cell
+                for id in row.iter().rev() {
+                    self.move_block(*id, self.blocks[*id as usize] + offset);
+                }
+                for id in row.iter() {
+                    let block_target = self.blocks[*id as usize];
+                    moves.push(CellMove{ mover: GridCellType::MovingTile(*id), from: block_target - offset, to: block_target });
+                }
+                pushed = row;
+                target
+            },
+            _ => match self.walk_target(self.player, facing) {
+                Some(to) => to,
+                None => return Outcome::Blocked,
+            },
+        };
+
+        moves.insert(0, CellMove{ mover: GridCellType::Player, from: self.player, to });
+        self.grid.grid.insert(self.player, None);
+        self.grid.grid.insert(to, Some(GridCellType::Player));
+        self.player = to;
+        self.moves += 1;
+        //A chain push counts as one push
+        if !pushed.is_empty() { self.pushes += 1; }
+
+        let mut after = Vec::new();
+        if !pushed.is_empty() {
+            self.slide(&pushed, facing, &mut after);
+        }
+        for id in pushed.iter().rev() {
+            self.fall(*id, &mut after);
+        }
+        self.settle(facing, &mut after);
+
+        Outcome::Moved(Step{ facing, moves, after, pushed: !pushed.is_empty(), pulled: false, solved: self.is_solved() })
+    }
+
+    //Steps away from the block behind the player, the block follows into the cell the player leaves.
+    //Without a block to pull, or on a level without pulling, it is a usual step.
+    pub fn pull(&mut self, facing: Facing) -> Outcome {
+        let offset = facing.offset();
+        let from = self.player;
+        let target = from + offset;
+        let id = match self.cell(from - offset) {
+            Some(Some(GridCellType::MovingTile(id))) if self.rules.pull && self.is_walkable(target) && self.can_enter(target, facing)
+                && self.can_enter(from, facing) && !self.is_loaded(from - offset) => id,
+            _ => return self.step(facing),
+        };
+
+        self.grid.grid.insert(from, None);
+        self.grid.grid.insert(target, Some(GridCellType::Player));
+        self.player = target;
+        self.move_block(id, from);
+        self.moves += 1;
+        //A pull moves a block like a push and counts as one
+        self.pushes += 1;
+
+        let moves = vec![
+            CellMove{ mover: GridCellType::Player, from, to: target },
+            CellMove{ mover: GridCellType::MovingTile(id), from: from - offset, to: from },
+        ];
+        let mut after = Vec::new();
+        self.settle(facing, &mut after);
+        Outcome::Moved(Step{ facing, moves, after, pushed: false, pulled: true, solved: self.is_solved() })
+    }
+
+    //The end of every step: the player slides on over ice, then the conveyors move what stands on them
+    //and the blocks they move off an edge fall
+    fn settle(&mut self, facing: Facing, after: &mut Vec<Vec<CellMove>>) {
+        let offset = facing.offset();
+        while self.on_ice(self.player) && self.is_walkable(self.player + offset) && self.can_enter(self.player + offset, facing) {
+            let from = self.player;
+            self.grid.grid.insert(from, None);
+            self.grid.grid.insert(from + offset, Some(GridCellType::Player));
+            self.player = from + offset;
+            after.push(vec![CellMove{ mover: GridCellType::Player, from, to: self.player }]);
+        }
+
+        let conveyed = self.convey();
+        if conveyed.is_empty() { return; }
+        let blocks: Vec<i32> = conveyed.iter()
+            .filter_map(|cell_move| match cell_move.mover {
+                GridCellType::MovingTile(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+        after.push(conveyed);
+        for id in blocks {
+            self.fall(id, after);
+        }
+    }
+
+    //Everything on a conveyor goes one cell its way when there is room, all at the same time.
+    //Something leaving a conveyor makes room for the one behind it, so the conveyors are gone over
+    //until nothing more moves.
+    fn convey(&mut self) -> Vec<CellMove> {
+        let mut conveyed: Vec<CellMove> = Vec::new();
+        loop {
+            let mut moved = false;
+            for (cell, way) in self.conveyors.clone() {
+                let to = cell + way.offset();
+                let mover = match self.cell(cell) {
+                    Some(Some(mover)) if !conveyed.iter().any(|cell_move| cell_move.mover == mover) => mover,
+                    _ => continue,
+                };
+                let room = match mover {
+                    GridCellType::Player => self.is_walkable(to),
+                    GridCellType::MovingTile(_) => self.is_empty(to) && !self.is_loaded(cell),
+                    GridCellType::Tile(_) => false,
+                };
+                if !room || !self.can_enter(to, way) { continue; }
+
+                match mover {
+                    GridCellType::MovingTile(id) => self.move_block(id, to),
+                    _ => {
+                        self.grid.grid.insert(cell, None);
+                        self.grid.grid.insert(to, Some(GridCellType::Player));
+                        self.player = to;
+                    },
+                }
+                conveyed.push(CellMove{ mover, from: cell, to });
+                moved = true;
+            }
+            if !moved { return conveyed; }
+        }
+    }
+
+    //Something stands on the block, it is too heavy to move
+    fn is_loaded(&self, block: GridCell) -> bool {
+        matches!(self.cell(block + GridCell::new(0, 1, 0)), Some(Some(GridCellType::MovingTile(_) | GridCellType::Player)))
+    }
+
+    //The ids of the blocks in a row from this cell that one step pushes together, starting with the nearest.
+    //None when the row is longer than the level's chain rule, the cell after it isn't empty,
+    //something stands on one of the blocks, a gate or a door is in the way or there is a heavy block
+    //in a row of more than one.
+    pub fn pushed_row(&self, block: GridCell, facing: Facing) -> Option<Vec<i32>> {
+        let mut row = Vec::new();
+        let mut cell = block;
+        while let Some(Some(GridCellType::MovingTile(id))) = self.cell(cell) {
+            if row.len() == self.rules.chain || self.is_loaded(cell) || !self.can_enter(cell, facing) { return None; }
+            row.push(id);
+            cell = cell + facing.offset();
+        }
+        if row.is_empty() || !self.is_empty(cell) || !self.can_enter(cell, facing) { return None; }
+        //A heavy block only moves on its own
+        if row.len() > 1 && row.iter().any(|id| self.kinds[*id as usize] == BlockKind::Heavy) { return None; }
+        Some(row)
+    }
+
+    //Where the player ends up walking from a cell without pushing: the next cell on the same level,
+    //one level up onto a wall or a block it can't push, or one level down over the edge.
+    //A block in a hole is ground like any tile, so a filled hole is walked over.
+    pub fn walk_target(&self, from: GridCell, facing: Facing) -> Option<GridCell> {
+        let up = GridCell::new(0, 1, 0);
+        let target = from + facing.offset();
+        let to = match self.cell(target) {
+            Some(None) if self.has_ground(target) => Some(target),
+            //Deeper drops than one level aren't safe
+            Some(None) => Some(target - up).filter(|below| self.is_walkable(*below)),
+            Some(Some(GridCellType::Tile(_))) => self.climb(from, target),
+            Some(Some(GridCellType::MovingTile(_))) if self.pushed_row(target, facing).is_none() => self.climb(from, target),
+            _ => None,
+        };
+        to.filter(|to| self.can_enter(*to, facing))
+    }
+
+    //Stepping up needs room over the player's head and over the ledge
+    fn climb(&self, from: GridCell, ledge: GridCell) -> Option<GridCell> {
+        let up = GridCell::new(0, 1, 0);
+        Some(ledge + up).filter(|top| self.is_empty(from + up) && self.is_walkable(*top))
+    }
+
+    //Ice and conveyors move things without the player, steps on them can't be planned as plain walks
+    pub fn has_moving_floor(&self) -> bool {
+        !self.conveyors.is_empty()
+            || self.grid.grid.values().any(|tile| matches!(tile, Some(GridCellType::Tile(id)) if tile_behavior(*id) == TileBehavior::Ice))
+    }
+
+    fn move_block(&mut self, id: i32, to: GridCell) {
+        let from = self.blocks[id as usize];
+        self.grid.grid.insert(from, None);
+        self.grid.grid.insert(to, Some(GridCellType::MovingTile(id)));
+        self.blocks[id as usize] = to;
+    }
+
+    //On ice, the level's or the ice tiles under every block of it, the pushed row goes on until the cell
+    //after it isn't empty or one of its blocks is over a hole
+    fn slide(&mut self, row: &[i32], facing: Facing, slides: &mut Vec<Vec<CellMove>>) {
+        let offset = facing.offset();
+        let front = row[row.len() - 1];
+        loop {
+            let next = self.blocks[front as usize] + offset;
+            if !self.is_empty(next) || !self.can_enter(next, facing)
+                || row.iter().any(|id| !self.has_ground(self.blocks[*id as usize]))
+                || !(self.rules.slide || row.iter().all(|id| self.on_ice(self.blocks[*id as usize]))) {
+                return;
+            }
+            for id in row.iter().rev() {
+                self.move_block(*id, self.blocks[*id as usize] + offset);
+            }
+            slides.push(row.iter().map(|id| {
+                let to = self.blocks[*id as usize];
+                CellMove{ mover: GridCellType::MovingTile(*id), from: to - offset, to }
+            }).collect());
+        }
+    }
+
+    //A block drops while the cell below it is empty, it stops on the ground, on a wall, on another block
+    //or on a closed door. A fragile block that fell breaks where it lands.
+    fn fall(&mut self, id: i32, falls: &mut Vec<Vec<CellMove>>) {
+        let mut fell = false;
+        loop {
+            let from = self.blocks[id as usize];
+            let below = from + GridCell::new(0, -1, 0);
+            if !self.is_empty(below) || self.door_closed(below) { break; }
+            self.move_block(id, below);
+            falls.push(vec![CellMove{ mover: GridCellType::MovingTile(id), from, to: below }]);
+            fell = true;
+        }
+        if fell && self.kinds[id as usize] == BlockKind::Fragile {
+            let from = self.blocks[id as usize];
+            self.grid.grid.insert(from, None);
+            self.blocks[id as usize] = BROKEN;
+            falls.push(vec![CellMove{ mover: GridCellType::MovingTile(id), from, to: BROKEN }]);
+        }
+    }
+}
+
+//A puzzle from its layers, drawn from the ground up like the csv layers: a hex digit is a tile id,
+//'.' an empty cell. The rule tests of the other modules build their levels with it too.
+#[cfg(test)]
+pub fn layered(layers: &[&[&str]], player: (i32, i32, i32), blocks: &[(i32, i32, i32)], goals: &[(i32, i32, i32)]) -> PuzzleState {
+    let width = layers[0][0].len() as i32;
+    let length = layers[0].len() as i32;
+    let mut grid = GameGrid::new(width, length, layers.len() as i32);
+    for (y, rows) in layers.iter().enumerate() {
+        for (z, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if let Some(id) = c.to_digit(16) {
+                    grid.grid.insert(GridCell::new(x as i32, y as i32, z as i32), Some(GridCellType::Tile(id as i32)));
+                }
+            }
+        }
+    }
+    let blocks = blocks.iter().map(|(x, y, z)| GridCell::new(*x, *y, *z)).collect();
+    let goals = goals.iter().map(|(x, y, z)| Goal::new(GridCell::new(*x, *y, *z))).collect();
+    PuzzleState::new(grid, GridCell::new(player.0, player.1, player.2), blocks, goals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOOR: &[&str] = &["00000", "00000", "00000"];
+    const OPEN: &[&str] = &[".....", ".....", "....."];
+
+    #[test]
+    fn push_moves_the_block_and_the_player() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the push was blocked"),
+        };
+        assert!(step.pushed);
+        assert_eq!(puzzle.player, GridCell::new(1, 1, 1));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 1, 1)]);
+        assert_eq!(puzzle.cell(GridCell::new(2, 1, 1)), Some(Some(GridCellType::MovingTile(0))));
+        assert_eq!((puzzle.moves, puzzle.pushes), (1, 1));
+    }
+
+    #[test]
+    fn walls_and_blocks_stop_a_push() {
+        let mut walled = layered(&[FLOOR, &["..1..", "..1..", "..1.."]], (0, 1, 1), &[(1, 1, 1)], &[]);
+        assert_eq!(walled.step(Facing::Right), Outcome::Blocked);
+        assert_eq!(walled.blocks, vec![GridCell::new(1, 1, 1)]);
+
+        let mut crowded = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1), (2, 1, 1)], &[]);
+        assert_eq!(crowded.step(Facing::Right), Outcome::Blocked);
+        assert_eq!(crowded.player, GridCell::new(0, 1, 1));
+        assert_eq!((crowded.moves, crowded.pushes), (0, 0));
+    }
+
+    #[test]
+    fn a_block_pushed_over_a_hole_fills_it() {
+        let mut puzzle = layered(&[&["00000", "00.00", "00000"], OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the push was blocked"),
+        };
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 0, 1)]);
+        assert_eq!(step.after, vec![vec![CellMove{ mover: GridCellType::MovingTile(0), from: GridCell::new(2, 1, 1), to: GridCell::new(2, 0, 1) }]]);
+        //The filled hole is walked over like floor
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.player, GridCell::new(2, 1, 1));
+    }
+
+    #[test]
+    fn the_level_is_solved_when_every_goal_has_a_block() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1), (1, 1, 2)], &[(2, 1, 1), (1, 1, 2)]);
+        assert!(!puzzle.is_solved());
+        match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => assert!(step.solved),
+            Outcome::Blocked => panic!("the push was blocked"),
+        }
+        assert!(puzzle.is_solved());
+        puzzle.step(Facing::Right);
+        assert!(!puzzle.is_solved());
+    }
+
+    #[test]
+    fn the_player_climbs_one_level_and_drops_one_level() {
+        let mut puzzle = layered(&[FLOOR, &["..1..", "..1..", "..1.."], OPEN], (1, 1, 1), &[], &[]);
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.player, GridCell::new(2, 2, 1));
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.player, GridCell::new(3, 1, 1));
+    }
+
+    #[test]
+    fn two_levels_are_too_high_and_too_deep() {
+        let tower = &["..1..", "..1..", "..1.."];
+        let mut climbing = layered(&[FLOOR, tower, tower, OPEN], (1, 1, 1), &[], &[]);
+        assert_eq!(climbing.step(Facing::Right), Outcome::Blocked);
+
+        let mut dropping = layered(&[FLOOR, tower, tower, OPEN], (2, 3, 1), &[], &[]);
+        assert_eq!(dropping.step(Facing::Right), Outcome::Blocked);
+        assert_eq!(dropping.player, GridCell::new(2, 3, 1));
+    }
+
+    #[test]
+    fn a_block_pushed_off_a_ledge_lands_on_the_block_below() {
+        let ledge = &["111..", "111..", "111.."];
+        let mut puzzle = layered(&[FLOOR, ledge, OPEN], (1, 2, 1), &[(2, 2, 1), (3, 1, 1)], &[]);
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(3, 2, 1), GridCell::new(3, 1, 1)]);
+        //The block below carries the other one, it can't be pushed any more
+        assert_eq!(puzzle.pushed_row(GridCell::new(3, 1, 1), Facing::Right), None);
+    }
+
+    #[test]
+    fn the_player_climbs_a_block_it_cant_push() {
+        let mut puzzle = layered(&[FLOOR, &["...1.", "...1.", "...1."], OPEN], (1, 1, 1), &[(2, 1, 1)], &[]);
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.player, GridCell::new(2, 2, 1));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 1, 1)]);
+        assert_eq!(puzzle.pushes, 0);
+    }
+
+    #[test]
+    fn a_chain_pushes_a_row_of_blocks_as_one_push() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1), (2, 1, 1), (3, 1, 1)], &[]);
+        puzzle.rules.chain = 3;
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the chain push was blocked"),
+        };
+        assert_eq!(step.moves.len(), 4);
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 1, 1), GridCell::new(3, 1, 1), GridCell::new(4, 1, 1)]);
+        assert_eq!(puzzle.pushes, 1);
+        //The row is at the wall now
+        assert_eq!(puzzle.step(Facing::Right), Outcome::Blocked);
+    }
+
+    #[test]
+    fn a_row_longer_than_the_chain_doesnt_move() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1), (2, 1, 1), (3, 1, 1)], &[]);
+        puzzle.rules.chain = 2;
+        assert_eq!(puzzle.pushed_row(GridCell::new(1, 1, 1), Facing::Right), None);
+        assert_eq!(puzzle.pushed_row(GridCell::new(2, 1, 1), Facing::Right), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn pulling_brings_the_block_behind_along() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (2, 1, 1), &[(3, 1, 1)], &[]);
+        let pull = PlayerMove{ facing: Facing::Left, pull: true };
+        //Without the rule it is a usual step
+        let mut no_pulling = puzzle.clone();
+        assert!(matches!(no_pulling.play(pull), Outcome::Moved(step) if !step.pulled));
+        assert_eq!(no_pulling.blocks, vec![GridCell::new(3, 1, 1)]);
+
+        puzzle.rules.pull = true;
+        assert!(matches!(puzzle.play(pull), Outcome::Moved(step) if step.pulled));
+        assert_eq!(puzzle.player, GridCell::new(1, 1, 1));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 1, 1)]);
+        assert_eq!(puzzle.pushes, 1);
+    }
+
+    #[test]
+    fn a_pushed_block_slides_until_something_stops_it() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        puzzle.rules.slide = true;
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the push was blocked"),
+        };
+        assert_eq!(puzzle.blocks, vec![GridCell::new(4, 1, 1)]);
+        assert_eq!(step.after.len(), 2);
+        //The player doesn't slide on a level with the rule, only on ice tiles
+        assert_eq!(puzzle.player, GridCell::new(1, 1, 1));
+    }
+
+    #[test]
+    fn a_sliding_block_falls_into_a_hole() {
+        let mut puzzle = layered(&[&["00000", "000.0", "00000"], OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        puzzle.rules.slide = true;
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(3, 0, 1)]);
+    }
+
+    #[test]
+    fn the_player_and_the_blocks_slide_over_ice() {
+        let ice = &["00000", "02220", "00000"];
+        let mut walking = layered(&[ice, OPEN], (0, 1, 1), &[], &[]);
+        assert!(matches!(walking.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(walking.player, GridCell::new(4, 1, 1));
+
+        let mut pushing = layered(&[ice, OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        assert!(matches!(pushing.step(Facing::Right), Outcome::Moved(_)));
+        //The block stops where the ice ends, the player slides up to it
+        assert_eq!(pushing.blocks, vec![GridCell::new(4, 1, 1)]);
+        assert_eq!(pushing.player, GridCell::new(3, 1, 1));
+    }
+
+    #[test]
+    fn conveyors_carry_what_stands_on_them() {
+        let mut puzzle = layered(&[&["00000", "05000", "00000"], OPEN], (0, 1, 1), &[], &[]);
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the step was blocked"),
+        };
+        assert_eq!(puzzle.player, GridCell::new(2, 1, 1));
+        assert_eq!(step.after, vec![vec![CellMove{ mover: GridCellType::Player, from: GridCell::new(1, 1, 1), to: GridCell::new(2, 1, 1) }]]);
+    }
+
+    #[test]
+    fn gates_only_let_things_in_going_their_way() {
+        let gate = &["00000", "00900", "00000"];
+        let mut with_the_gate = layered(&[gate, OPEN], (1, 1, 1), &[], &[]);
+        assert!(matches!(with_the_gate.step(Facing::Right), Outcome::Moved(_)));
+        let mut against_the_gate = layered(&[gate, OPEN], (3, 1, 1), &[], &[]);
+        assert_eq!(against_the_gate.step(Facing::Left), Outcome::Blocked);
+        let pushing_against = layered(&[gate, OPEN], (4, 1, 1), &[(3, 1, 1)], &[]);
+        assert_eq!(pushing_against.pushed_row(GridCell::new(3, 1, 1), Facing::Left), None);
+    }
+
+    #[test]
+    fn a_block_on_the_switch_opens_the_doors() {
+        let mut puzzle = layered(&[&["00000", "000c0", "0b000"], OPEN], (1, 1, 0), &[(1, 1, 1)], &[]);
+        let mut closed = puzzle.clone();
+        closed.place(GridCell::new(2, 1, 1), &[GridCell::new(1, 1, 1)]);
+        assert!(!closed.doors_open());
+        assert_eq!(closed.step(Facing::Right), Outcome::Blocked);
+
+        assert!(matches!(puzzle.step(Facing::Down), Outcome::Moved(_)));
+        assert!(puzzle.doors_open());
+        puzzle.step(Facing::Right);
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.player, GridCell::new(3, 1, 1));
+    }
+
+    #[test]
+    fn a_heavy_block_is_only_pushed_on_its_own() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1), (2, 1, 1)], &[]);
+        puzzle.rules.chain = 2;
+        puzzle.kinds[1] = BlockKind::Heavy;
+        assert_eq!(puzzle.pushed_row(GridCell::new(1, 1, 1), Facing::Right), None);
+        assert_eq!(puzzle.pushed_row(GridCell::new(2, 1, 1), Facing::Right), Some(vec![1]));
+    }
+
+    #[test]
+    fn a_fragile_block_breaks_when_it_falls() {
+        let mut puzzle = layered(&[&["00000", "00.00", "00000"], OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        puzzle.kinds[0] = BlockKind::Fragile;
+        let step = match puzzle.step(Facing::Right) {
+            Outcome::Moved(step) => step,
+            Outcome::Blocked => panic!("the push was blocked"),
+        };
+        assert_eq!(puzzle.blocks, vec![BROKEN]);
+        assert_eq!(step.after.last(), Some(&vec![CellMove{ mover: GridCellType::MovingTile(0), from: GridCell::new(2, 0, 1), to: BROKEN }]));
+        //The hole stays open
+        assert!(puzzle.is_empty(GridCell::new(2, 0, 1)));
+        assert_eq!(puzzle.step(Facing::Right), Outcome::Blocked);
+    }
+
+    #[test]
+    fn a_fragile_block_pushed_on_the_floor_stays_whole() {
+        let mut puzzle = layered(&[FLOOR, OPEN], (0, 1, 1), &[(1, 1, 1)], &[]);
+        puzzle.kinds[0] = BlockKind::Fragile;
+        assert!(matches!(puzzle.step(Facing::Right), Outcome::Moved(_)));
+        assert_eq!(puzzle.blocks, vec![GridCell::new(2, 1, 1)]);
+    }
+}
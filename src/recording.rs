@@ -0,0 +1,139 @@
+use std::{fs, path::PathBuf};
+use bevy::prelude::*;
+use super::{grid_physics::*, PlayerSpawned};
+use super::hint::SolutionPlayback;
+use super::history::{place_entities, MoveHistory};
+use super::input::Action;
+use super::level_description::file_name;
+use super::levels::{CurrentLevel, LevelList};
+use super::puzzle::PuzzleState;
+use super::replay::parse;
+use super::save_game::save_folder;
+
+//Playback speeds go from a quarter to eight times the normal speed
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+
+//How many times faster than normal the solution or a replay is played
+pub struct PlaybackSpeed(pub f32);
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin{
+    fn build(&self, app: &mut App){
+        app
+        .insert_resource(PlaybackSpeed(1.0))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(save_solution))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(replay_input.before(start_moves)))
+        .add_system_set(SystemSet::on_update(PlayerSpawned::Yes).with_system(playback_speed.before(start_moves)))
+        .add_system_set(SystemSet::on_exit(PlayerSpawned::Yes).with_system(save_attempt));
+    }
+}
+
+//The replays of a level: the last solution and the last attempt left unfinished
+fn replay_path(level_id: &str, attempt: bool) -> PathBuf {
+    let name = file_name(level_id);
+    let file = if attempt { format!("{}-attempt.txt", name) } else { format!("{}.txt", name) };
+    replay_folder().join(file)
+}
+
+fn replay_folder() -> PathBuf {
+    save_folder().join("replays")
+}
+
+fn write_replay(path: PathBuf, replay: &str) {
+    match fs::create_dir_all(replay_folder()).and_then(|_| fs::write(&path, replay)) {
+        Ok(_) => info!("Replay saved to {}", path.display()),
+        Err(error) => error!("Couldn't write the replay {}: {}", path.display(), error),
+    }
+}
+
+//The step that solves the level writes the solution, the animation doesn't have to finish
+fn save_solution(
+    puzzle: Res<PuzzleState>,
+    history: Res<MoveHistory>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+){
+    if !puzzle.is_changed() || !puzzle.is_solved() || history.undo.is_empty() { return; }
+    write_replay(replay_path(&levels.0[current_level.0].id(), false), &history.replay());
+}
+
+//Leaving or resetting a level keeps what was played, for a bug report or to go on watching it
+fn save_attempt(
+    puzzle: Res<PuzzleState>,
+    history: Res<MoveHistory>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+){
+    if puzzle.is_solved() || history.undo.is_empty() { return; }
+    write_replay(replay_path(&levels.0[current_level.0].id(), true), &history.replay());
+}
+
+//Play replay goes back to the start of the level and plays the saved solution, or the last attempt
+//when there is none. Pressed again it stops, like play solution.
+fn replay_input(
+    mut query: Query<(&GridEntity, &mut CurrentPosition, &mut Transform, &mut GridVelocity, &mut ToMove, &mut Visibility)>,
+    actions: Res<Input<Action>>,
+    mut playback: ResMut<SolutionPlayback>,
+    mut puzzle: ResMut<PuzzleState>,
+    mut history: ResMut<MoveHistory>,
+    pending: Res<PendingMoves>,
+    block: Res<BlockNextMove>,
+    levels: Res<LevelList>,
+    current_level: Res<CurrentLevel>,
+){
+    if !actions.just_pressed(Action::PlayReplay) { return; }
+    if !playback.0.is_empty() {
+        playback.0.clear();
+        return;
+    }
+    if block.0 || !pending.0.is_empty() || puzzle.is_solved() { return; }
+    if query.iter().any(|(_, _, _, _, to_move, _)| to_move.0) { return; }
+
+    let id = levels.0[current_level.0].id();
+    let path = replay_path(&id, false);
+    let path = if path.exists() { path } else { replay_path(&id, true) };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => {
+            info!("There is no replay of {} yet", levels.0[current_level.0].name);
+            return;
+        },
+    };
+    let steps = match parse(&text) {
+        Ok(steps) => steps,
+        Err(error) => {
+            warn!("Couldn't play the replay {}: {}", path.display(), error);
+            return;
+        },
+    };
+
+    if let Some(start) = history.start() {
+        *puzzle = start.clone();
+    }
+    history.clear();
+    place_entities(&puzzle, &mut query);
+    playback.0 = steps.into_iter().collect();
+}
+
+//Faster and slower change the speed of any playback, the player's own moves keep the normal speed
+fn playback_speed(
+    actions: Res<Input<Action>>,
+    playback: Res<SolutionPlayback>,
+    mut speed: ResMut<PlaybackSpeed>,
+    mut timer: ResMut<MoveTimer>,
+){
+    if actions.just_pressed(Action::PlaybackFaster) {
+        speed.0 = (speed.0 * 2.0).min(MAX_SPEED);
+        info!("Playback speed x{}", speed.0);
+    }
+    if actions.just_pressed(Action::PlaybackSlower) {
+        speed.0 = (speed.0 / 2.0).max(MIN_SPEED);
+        info!("Playback speed x{}", speed.0);
+    }
+    let seconds = if playback.0.is_empty() { MOVE_SECONDS } else { MOVE_SECONDS / speed.0 };
+    if timer.duration().as_secs_f32() != seconds {
+        timer.set_duration(std::time::Duration::from_secs_f32(seconds));
+    }
+}
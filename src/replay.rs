@@ -0,0 +1,77 @@
+use super::puzzle::{Facing, PlayerMove, Step};
+
+//Steps written in the usual sokoban notation: l, u, r and d for a walk, upper case when it pushed a block.
+//A pull is written in upper case too, a step can't push and pull at once.
+pub fn step_char(step: &Step) -> char {
+    let c = match step.facing {
+        Facing::Left => 'l',
+        Facing::Up => 'u',
+        Facing::Right => 'r',
+        Facing::Down => 'd',
+    };
+    if step.pushed || step.pulled { c.to_ascii_uppercase() } else { c }
+}
+
+//The steps of a written replay. Whether a step pushes follows from the level, upper case steps
+//only pull when there is nothing in front to push. Whitespace is skipped, lines can be wrapped.
+pub fn parse(text: &str) -> Result<Vec<PlayerMove>, String> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .enumerate()
+        .map(|(i, c)| {
+            let facing = match c.to_ascii_lowercase() {
+                'l' => Facing::Left,
+                'u' => Facing::Up,
+                'r' => Facing::Right,
+                'd' => Facing::Down,
+                _ => return Err(format!("Unknown step '{}' at step {}", c, i + 1)),
+            };
+            Ok(PlayerMove{ facing, pull: c.is_ascii_uppercase() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::puzzle::{layered, Outcome, PuzzleState};
+
+    //Plays the moves and writes the steps down the way the move history does
+    fn play(puzzle: &mut PuzzleState, moves: &[PlayerMove]) -> String {
+        moves.iter().map(|player_move| match puzzle.play(*player_move) {
+            Outcome::Moved(step) => step_char(&step),
+            Outcome::Blocked => panic!("the step {:?} was blocked", player_move),
+        }).collect()
+    }
+
+    #[test]
+    fn a_written_replay_plays_back_the_same_steps() {
+        let mut start = layered(&[&["00000", "00000", "00000"], &[".....", ".....", "....."]], (0, 1, 1), &[(1, 1, 1)], &[]);
+        start.rules.pull = true;
+        let moves = [
+            PlayerMove{ facing: Facing::Right, pull: false },
+            PlayerMove{ facing: Facing::Left, pull: true },
+            PlayerMove{ facing: Facing::Up, pull: false },
+            PlayerMove{ facing: Facing::Down, pull: false },
+        ];
+        let mut played = start.clone();
+        let text = play(&mut played, &moves);
+        assert_eq!(text, "RLud");
+
+        //The push reads back as a pull, with nothing behind the player to pull it pushes all the same
+        let read = parse(&text).unwrap();
+        assert_eq!(read[0], PlayerMove{ facing: Facing::Right, pull: true });
+        assert_eq!(&read[1..], &moves[1..]);
+        let mut replayed = start.clone();
+        assert_eq!(play(&mut replayed, &read), text);
+        assert_eq!((replayed.player, &replayed.blocks), (played.player, &played.blocks));
+    }
+
+    #[test]
+    fn whitespace_is_skipped_and_other_characters_are_refused() {
+        assert_eq!(parse("rR\n l ").unwrap().len(), 3);
+        assert_eq!(parse("").unwrap(), vec![]);
+        assert_eq!(parse("ur x").err().unwrap(), "Unknown step 'x' at step 3");
+        assert!(parse("r1").is_err());
+    }
+}
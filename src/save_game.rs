@@ -0,0 +1,94 @@
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::PathBuf};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SAVE_FILE: &str = "save.ron";
+
+//How far the player got, kept between runs in the user data folder
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SaveGame {
+    //The game's own levels opened by finishing the one before them, by level id. The first one is always open.
+    pub unlocked_levels: BTreeSet<String>,
+    //The id of the level "Continue" starts
+    pub continue_level: Option<String>,
+    //Completion and best scores by level id, so adding or moving level files keeps them
+    pub levels: BTreeMap<String, LevelProgress>,
+    //Everything it took to finish the levels, every time one is finished, for the credits
+    pub total_moves: usize,
+    pub total_pushes: usize,
+    pub total_seconds: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LevelProgress {
+    pub completed: bool,
+    pub best_moves: Option<usize>,
+    pub best_pushes: Option<usize>,
+}
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin{
+    fn build(&self, app: &mut App){
+        app.insert_resource(SaveGame::load());
+    }
+}
+
+//The folder of the save game, next to it go the other files the game writes
+pub fn save_folder() -> PathBuf {
+    dirs::data_dir().unwrap_or_else(|| PathBuf::from(".")).join("carrier_clone")
+}
+
+impl SaveGame {
+    //A missing save is a new game, a broken one is reported and started over
+    pub fn load() -> Self {
+        let path = save_folder().join(SAVE_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return SaveGame::default(),
+        };
+        ron::from_str(&text).unwrap_or_else(|error| {
+            warn!("Ignoring the save game {}: {}", path.display(), error);
+            SaveGame::default()
+        })
+    }
+
+    pub fn write(&self) {
+        let folder = save_folder();
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(error) => {
+                error!("Couldn't write the save game: {}", error);
+                return;
+            },
+        };
+        if let Err(error) = fs::create_dir_all(&folder).and_then(|_| fs::write(folder.join(SAVE_FILE), text)) {
+            error!("Couldn't write the save game in {}: {}", folder.display(), error);
+        }
+    }
+
+    pub fn has_progress(&self) -> bool {
+        self.continue_level.is_some() || !self.levels.is_empty()
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked_levels.contains(id)
+    }
+
+    //Keeps the best move and push counts, each one on its own
+    pub fn complete(&mut self, id: &str, moves: usize, pushes: usize, seconds: f32) {
+        let progress = self.levels.entry(id.to_string()).or_default();
+        progress.completed = true;
+        progress.best_moves = Some(progress.best_moves.map_or(moves, |best| best.min(moves)));
+        progress.best_pushes = Some(progress.best_pushes.map_or(pushes, |best| best.min(pushes)));
+        self.total_moves += moves;
+        self.total_pushes += pushes;
+        self.total_seconds += seconds;
+    }
+
+    //Finishing one of the game's own levels opens the one after it
+    pub fn unlock(&mut self, id: &str) {
+        self.unlocked_levels.insert(id.to_string());
+    }
+}
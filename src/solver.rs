@@ -0,0 +1,331 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet, VecDeque}};
+use std::sync::atomic::{AtomicBool, Ordering};
+use super::game_grid::{GridCell, GridCellType};
+use super::goals::{BlockColor, BlockKind};
+use super::puzzle::{Facing, Outcome, PlayerMove, PuzzleState, BROKEN};
+use super::deadlock::{DIRECTIONS, distances_to_each_goal, dead_squares, is_frozen_around, is_lost};
+
+//How many positions the solver looks at before giving up
+pub const SEARCH_LIMIT: usize = 300_000;
+
+//The heuristic counts a bit more than the pushes already made, big levels are solved
+//much faster and the solutions are still close to the shortest
+const HEURISTIC_WEIGHT: usize = 2;
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    //Every player step, walking, pushing and pulling, from the start to the solved puzzle
+    pub steps: Vec<PlayerMove>,
+    pub pushes: usize,
+}
+
+//Where the player can get to and where the blocks are, blocks that can swap places sorted the same way
+type Position = (GridCell, Vec<(Option<BlockColor>, BlockKind, GridCell)>);
+
+struct Node {
+    player: GridCell,
+    blocks: Vec<GridCell>,
+    parent: Option<usize>,
+    //The walk to the block and the push or pull that made this node from its parent
+    steps: Vec<PlayerMove>,
+    pushes: usize,
+}
+
+//A* over the pushes, and the pulls on levels that allow them, the walking in between is found with
+//a breadth first search. Every push and pull is played with `PuzzleState::play`, so the solver follows
+//the same rules as the game.
+//On ice and conveyors a plain step can move things too, there every single step is searched.
+//Blocks are pushed on every height the player can climb or drop to.
+pub fn solve(start: &PuzzleState, limit: usize) -> Option<Solution> {
+    solve_until(start, limit, &AtomicBool::new(false))
+}
+
+//The same search, given up as soon as the flag is set from another thread
+pub fn solve_until(start: &PuzzleState, limit: usize, cancel: &AtomicBool) -> Option<Solution> {
+    let distances = distances_to_each_goal(start);
+    let dead = dead_squares(start);
+    let pulls: &[bool] = if start.rules.pull { &[false, true] } else { &[false] };
+    let by_steps = start.has_moving_floor();
+
+    let mut work = start.clone();
+    let mut nodes = vec![Node{ player: start.player, blocks: start.blocks.clone(), parent: None, steps: Vec::new(), pushes: 0 }];
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((0, 0, 0)));
+    let mut closed: HashSet<Position> = HashSet::new();
+
+    while let Some(Reverse((_, _, index))) = open.pop() {
+        if closed.len() >= limit || cancel.load(Ordering::Relaxed) { return None; }
+
+        let player = nodes[index].player;
+        let blocks = nodes[index].blocks.clone();
+        work.place(player, &blocks);
+        if work.is_solved() {
+            return Some(solution(&nodes, index));
+        }
+
+        let reach = if by_steps { HashMap::from([(player, None)]) } else { reachable(&work) };
+        //Blocks of the same color and kind can swap places, the position stays the same
+        let mut sorted_blocks: Vec<(Option<BlockColor>, BlockKind, GridCell)> = blocks.iter().enumerate()
+            .map(|(id, cell)| (work.colors[id], work.kinds[id], *cell))
+            .collect();
+        sorted_blocks.sort_by_key(|(color, kind, cell)| (*color, *kind, cell.y, cell.z, cell.x));
+        let corner = *reach.keys().min_by_key(|cell| (cell.y, cell.z, cell.x)).unwrap();
+        if !closed.insert((corner, sorted_blocks)) { continue; }
+
+        //Every step from where the player stands, or every push and pull the player can walk to.
+        //A push is made from behind the block, a pull from the cell the block goes to.
+        let tries = DIRECTIONS.into_iter().flat_map(|facing| pulls.iter().map(move |pull| PlayerMove{ facing, pull: *pull }));
+        let candidates: Vec<(GridCell, PlayerMove)> = if by_steps {
+            tries.map(|player_move| (player, player_move)).collect()
+        } else {
+            blocks.iter()
+                .filter(|block| **block != BROKEN)
+                .flat_map(|block| tries.clone().map(move |player_move| {
+                    let offset = player_move.facing.offset();
+                    (if player_move.pull { *block + offset } else { *block - offset }, player_move)
+                }))
+                .filter(|(stand, _)| reach.contains_key(stand))
+                .collect()
+        };
+
+        for (stand, player_move) in candidates {
+            //The step finds out whether the block, or the row of blocks, can move
+            work.place(stand, &blocks);
+            if let Outcome::Moved(step) = work.play(player_move) {
+                let moved_block = if player_move.pull { step.pulled } else { step.pushed };
+                if moved_block || by_steps {
+                    //Where the moved blocks ended up, after sliding and falling. The lost ones are counted on their own,
+                    //a block that filled a hole looks frozen among the floor tiles.
+                    let moved_to: Vec<GridCell> = step.moves.iter()
+                        .filter_map(|cell_move| match cell_move.mover {
+                            GridCellType::MovingTile(id) => Some(work.blocks[id as usize]),
+                            _ => None,
+                        })
+                        .filter(|cell| !is_lost(&work, *cell))
+                        .collect();
+                    if !is_deadlocked(&work, &moved_to, &dead) {
+                        let pushes = nodes[index].pushes + moved_block as usize;
+                        let estimate = heuristic(&work, &distances);
+                        let mut steps = walk(&reach, stand);
+                        steps.push(player_move);
+                        nodes.push(Node{ player: work.player, blocks: work.blocks.clone(), parent: Some(index), steps, pushes });
+                        open.push(Reverse((pushes + HEURISTIC_WEIGHT * estimate, estimate, nodes.len() - 1)));
+                    }
+                }
+            }
+            work.place(player, &blocks);
+        }
+    }
+    None
+}
+
+//The next push or pull the solver would make from this position, with the cell the block ends up in
+pub fn next_push(start: &PuzzleState, solution: &Solution) -> Option<(i32, GridCell)> {
+    let mut puzzle = start.clone();
+    for player_move in solution.steps.iter() {
+        if let Outcome::Moved(step) = puzzle.play(*player_move) {
+            if let Some(push) = step.moves.get(1) {
+                if let GridCellType::MovingTile(id) = push.mover {
+                    return Some((id, puzzle.blocks[id as usize]));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn solution(nodes: &[Node], mut index: usize) -> Solution {
+    let pushes = nodes[index].pushes;
+    let mut parts = Vec::new();
+    loop {
+        parts.push(nodes[index].steps.clone());
+        match nodes[index].parent {
+            Some(parent) => index = parent,
+            None => break,
+        }
+    }
+    Solution { steps: parts.into_iter().rev().flatten().collect(), pushes }
+}
+
+//Every cell the player can walk to, climbing and dropping included, with the cell and the step it was reached from
+fn reachable(puzzle: &PuzzleState) -> HashMap<GridCell, Option<(GridCell, Facing)>> {
+    let mut reach = HashMap::new();
+    let mut queue = VecDeque::new();
+    reach.insert(puzzle.player, None);
+    queue.push_back(puzzle.player);
+    while let Some(cell) = queue.pop_front() {
+        for facing in DIRECTIONS {
+            let next = match puzzle.walk_target(cell, facing) {
+                Some(next) if !reach.contains_key(&next) => next,
+                _ => continue,
+            };
+            reach.insert(next, Some((cell, facing)));
+            queue.push_back(next);
+        }
+    }
+    reach
+}
+
+fn walk(reach: &HashMap<GridCell, Option<(GridCell, Facing)>>, mut cell: GridCell) -> Vec<PlayerMove> {
+    let mut steps = Vec::new();
+    while let Some(Some((from, facing))) = reach.get(&cell) {
+        steps.push(PlayerMove{ facing: *facing, pull: false });
+        cell = *from;
+    }
+    steps.reverse();
+    steps
+}
+
+//Pushes still needed if the blocks went to goals of their own, the closest pairs are matched first.
+//Matching every goal to one block keeps the solver from piling all the blocks on the nearest goal,
+//and a colored goal is only matched to the blocks of its color.
+fn heuristic(puzzle: &PuzzleState, distances: &[HashMap<GridCell, usize>]) -> usize {
+    let far = puzzle.grid.grid.len();
+    let blocks: Vec<(&GridCell, Option<BlockColor>)> = puzzle.blocks.iter().zip(puzzle.colors.iter().copied())
+        .filter(|(block, _)| !is_lost(puzzle, **block))
+        .collect();
+    let mut pairs = Vec::new();
+    for (goal, to_goal) in distances.iter().enumerate() {
+        for (block, (cell, color)) in blocks.iter().enumerate() {
+            let distance = if puzzle.goals[goal].accepts(*color) { to_goal.get(*cell).copied().unwrap_or(far) } else { far };
+            pairs.push((distance, goal, block));
+        }
+    }
+    pairs.sort();
+
+    let mut goal_done = vec![false; distances.len()];
+    let mut block_done = vec![false; blocks.len()];
+    let mut estimate = 0;
+    for (distance, goal, block) in pairs {
+        if goal_done[goal] || block_done[block] { continue; }
+        goal_done[goal] = true;
+        block_done[block] = true;
+        estimate += distance;
+    }
+    estimate
+}
+
+//Fewer usable blocks than goals, more blocks on dead squares than the level can spare, or a pushed block got frozen
+fn is_deadlocked(puzzle: &PuzzleState, pushed_to: &[GridCell], dead: &HashSet<GridCell>) -> bool {
+    let usable: Vec<&GridCell> = puzzle.blocks.iter().filter(|block| !is_lost(puzzle, **block)).collect();
+    if usable.len() < puzzle.goals.len() { return true; }
+    let spare = usable.len() - puzzle.goals.len();
+    let on_dead = usable.iter().filter(|block| dead.contains(block)).count();
+    on_dead > spare || pushed_to.iter().any(|cell| is_frozen_around(puzzle, *cell))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::puzzle::layered;
+    use super::super::xsb::board;
+
+    //Plays the steps like the game would, every one of them has to move
+    fn play_all(puzzle: &mut PuzzleState, steps: &[PlayerMove]) {
+        for player_move in steps {
+            assert!(matches!(puzzle.play(*player_move), Outcome::Moved(_)), "the step {:?} was blocked", player_move);
+        }
+    }
+
+    #[test]
+    fn solves_a_small_level() {
+        let start = board("\
+#######
+#     #
+# $@$ #
+#.   .#
+#######
+");
+        let solution = solve(&start, SEARCH_LIMIT).expect("no solution found");
+        let mut puzzle = start.clone();
+        play_all(&mut puzzle, &solution.steps);
+        assert!(puzzle.is_solved());
+        assert_eq!(solution.pushes, puzzle.pushes);
+    }
+
+    #[test]
+    fn solves_a_level_that_needs_walking_around_the_blocks() {
+        let start = board("\
+  #####
+###   #
+#.@$  #
+### $.#
+#.##$ #
+# # . ##
+#$ *$$.#
+#   .  #
+########
+");
+        let solution = solve(&start, SEARCH_LIMIT).expect("no solution found");
+        let mut puzzle = start.clone();
+        play_all(&mut puzzle, &solution.steps);
+        assert!(puzzle.is_solved());
+    }
+
+    #[test]
+    fn solves_a_level_with_the_block_on_higher_ground() {
+        //The player climbs the ledge to get behind the block and pushes it off onto the goal below
+        let ledge: &[&str] = &["111..", "111..", "111.."];
+        let start = layered(&[&["00000", "00000", "00000"], ledge, &[".....", ".....", "....."]], (4, 1, 1), &[(1, 2, 1)], &[(3, 1, 1)]);
+        let solution = solve(&start, SEARCH_LIMIT).expect("no solution found");
+        let mut puzzle = start.clone();
+        play_all(&mut puzzle, &solution.steps);
+        assert!(puzzle.is_solved());
+        assert_eq!(solution.pushes, 2);
+    }
+
+    #[test]
+    fn a_solved_level_needs_no_steps() {
+        let start = board("\
+#####
+#@* #
+#####
+");
+        let solution = solve(&start, SEARCH_LIMIT).expect("no solution found");
+        assert!(solution.steps.is_empty());
+        assert_eq!(solution.pushes, 0);
+    }
+
+    #[test]
+    fn a_deadlocked_position_has_no_solution() {
+        //The box in the corner can never be pushed out again
+        let cornered = board("\
+######
+#$ @ #
+#   .#
+######
+");
+        assert!(solve(&cornered, SEARCH_LIMIT).is_none());
+
+        //Two boxes side by side against the wall, neither can move along it
+        let against_wall = board("\
+#######
+#  $$ #
+#  @  #
+#.  . #
+#######
+");
+        assert!(solve(&against_wall, SEARCH_LIMIT).is_none());
+    }
+
+    #[test]
+    fn a_cancelled_search_gives_up() {
+        let start = board("\
+#######
+#@ $ .#
+#######
+");
+        assert!(solve_until(&start, SEARCH_LIMIT, &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn next_push_is_the_first_push_of_the_solution() {
+        let start = board("\
+#######
+#@ $ .#
+#######
+");
+        let solution = solve(&start, SEARCH_LIMIT).expect("no solution found");
+        assert_eq!(next_push(&start, &solution), Some((0, GridCell::new(4, 1, 1))));
+    }
+}
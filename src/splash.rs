@@ -11,7 +11,7 @@ struct OnSplashScreen;
 struct LogoTimer(Timer);
 
 #[derive(Component, Deref, DerefMut)]
-struct FadeEffectTimer(Timer);
+pub struct FadeEffectTimer(pub Timer);
 
 #[derive(Component)]
 pub struct LogoNum(u8);
@@ -101,15 +101,19 @@ fn countdown(mut game_state: ResMut<State<GameState>>,
 pub fn fade_in_out(timer: &mut Timer, time: &Res<Time>, sprite: &mut Sprite){
     timer.tick(time.delta());
     if !timer.finished(){
-        let alpha = if timer.percent() < 0.3 {
-            timer.percent() * 2.0
-        } 
-        else if timer.percent() > 0.3 && timer.percent() < 0.7{
-            1.
-        }
-        else {
-            timer.percent_left() * 2.0
-        };
-        sprite.color.set_a(alpha);
+        sprite.color.set_a(fade_alpha(timer));
+    }
+}
+
+//Fades in, stays and fades out again over the length of the timer
+pub fn fade_alpha(timer: &Timer) -> f32 {
+    if timer.percent() < 0.3 {
+        timer.percent() * 2.0
+    }
+    else if timer.percent() > 0.3 && timer.percent() < 0.7{
+        1.
+    }
+    else {
+        timer.percent_left() * 2.0
     }
 }
\ No newline at end of file
@@ -0,0 +1,284 @@
+use std::{fs, path::Path};
+use roxmltree::{Document, Node};
+use super::game_grid::{GameGrid, GridCell, GridCellType};
+use super::goals::{Block, BlockColor, BlockKind, Goal};
+use super::level_description::{Level, LevelCamera, LevelError, LevelRules, LevelSize};
+
+//Tiled keeps the flip flags in the three highest bits of a gid
+const FLIP_FLAGS: u32 = 0xE000_0000;
+
+//Objects are put on this y level unless their object layer has a "y" property
+const DEFAULT_OBJECT_Y: i32 = 1;
+
+//Builds a level straight from a Tiled map.
+//Every tile layer is one y level of the grid, starting from the ground, and
+//the object layers hold the "player", "block" and "goal" objects. Blocks and goals take a "color" property
+//and blocks a "kind" property.
+pub fn load_tmx(path: &Path) -> Result<Level, LevelError> {
+    let text = fs::read_to_string(path).map_err(|e| LevelError::Io(path.to_path_buf(), e))?;
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    parse_tmx(&text, &name).map_err(|e| LevelError::Parse(path.to_path_buf(), e))
+}
+
+//The level of a map, named by its "name" property or else by the name given
+pub fn parse_tmx(text: &str, name: &str) -> Result<Level, String> {
+    let document = Document::parse(text).map_err(|e| e.to_string())?;
+    let map = document.root_element();
+
+    let width = int_attribute(&map, "width")?;
+    let length = int_attribute(&map, "height")?;
+    let tile_width = int_attribute(&map, "tilewidth")? as f32;
+    let tile_length = int_attribute(&map, "tileheight")? as f32;
+
+    let mut first_gids = Vec::new();
+    for tileset in map.children().filter(|node| node.has_tag_name("tileset")) {
+        first_gids.push(int_attribute(&tileset, "firstgid")? as u32);
+    }
+
+    let layers: Vec<Node> = map.children().filter(|node| node.has_tag_name("layer")).collect();
+    let size = LevelSize { width, length, height: layers.len() as i32 };
+    let mut grid = GameGrid::new(size.width, size.length, size.height);
+
+    for (y, layer) in layers.iter().enumerate() {
+        let gids = layer_gids(layer)?;
+        if gids.len() != (width * length) as usize {
+            return Err(format!("layer {} has {} tiles but the map is {}x{}", y, gids.len(), width, length));
+        }
+        for (index, gid) in gids.into_iter().enumerate() {
+            if let Some(id) = tile_id(gid, &first_gids) {
+                let cell = GridCell::new(index as i32 % width, y as i32, index as i32 / width);
+                grid.grid.insert(cell, Some(GridCellType::Tile(id)));
+            }
+        }
+    }
+
+    let mut player = None;
+    let mut blocks = Vec::new();
+    let mut goals = Vec::new();
+    for group in map.children().filter(|node| node.has_tag_name("objectgroup")) {
+        let y = match property(&group, "y") {
+            Some(value) => value.parse::<i32>().map_err(|e| format!("object layer y: {}", e))?,
+            None => DEFAULT_OBJECT_Y,
+        };
+
+        for object in group.children().filter(|node| node.has_tag_name("object")) {
+            let cell = object_cell(&object, y, tile_width, tile_length)?;
+            //Tiled 1.9 saves the object type as "class", older and newer versions as "type"
+            let kind = object.attribute("type").or_else(|| object.attribute("class")).unwrap_or("");
+            let color = match property(&object, "color") {
+                Some(name) => Some(BlockColor::from_name(name).ok_or_else(|| format!("{:?} isn't a block color", name))?),
+                None => None,
+            };
+            let block_kind = match property(&object, "kind") {
+                Some(name) => BlockKind::from_name(name).ok_or_else(|| format!("{:?} isn't a block kind", name))?,
+                None => BlockKind::Plain,
+            };
+            match kind {
+                "player" => player = Some(cell),
+                "block" => blocks.push(Block { cell, color, kind: block_kind }),
+                "goal" => goals.push(Goal { cell, color }),
+                _ => {},
+            }
+        }
+    }
+    let player = player.ok_or_else(|| "there is no player object".to_string())?;
+
+    let name = property(&map, "name").unwrap_or(name).to_string();
+
+    let mut camera = LevelCamera::framing(size);
+    if let Some(value) = property(&map, "camera_position") {
+        camera.position = vector_property(value)?;
+    }
+    if let Some(value) = property(&map, "camera_look_at") {
+        camera.look_at = vector_property(value)?;
+    }
+
+    let mut rules = LevelRules::default();
+    if let Some(value) = property(&map, "chain") {
+        rules.chain = value.parse::<usize>().map_err(|e| format!("chain property: {}", e))?;
+    }
+    if let Some(value) = property(&map, "pull") {
+        rules.pull = value.parse::<bool>().map_err(|e| format!("pull property: {}", e))?;
+    }
+    if let Some(value) = property(&map, "slide") {
+        rules.slide = value.parse::<bool>().map_err(|e| format!("slide property: {}", e))?;
+    }
+
+    Ok(Level { name, file: String::new(), collection: None, size, grid, player, blocks, goals, camera, rules })
+}
+
+fn int_attribute(node: &Node, name: &str) -> Result<i32, String> {
+    let value = node.attribute(name).ok_or_else(|| format!("<{}> has no {} attribute", node.tag_name().name(), name))?;
+    value.parse::<i32>().map_err(|e| format!("{} attribute: {}", name, e))
+}
+
+fn float_attribute(node: &Node, name: &str) -> Result<f32, String> {
+    match node.attribute(name) {
+        Some(value) => value.parse::<f32>().map_err(|e| format!("{} attribute: {}", name, e)),
+        None => Ok(0.0),
+    }
+}
+
+//Custom properties are saved as <properties><property name=".." value=".."/></properties>
+fn property<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .find(|child| child.has_tag_name("property") && child.attribute("name") == Some(name))
+        .and_then(|child| child.attribute("value"))
+}
+
+//"x,y,z" property values used for the camera
+fn vector_property(value: &str) -> Result<(f32, f32, f32), String> {
+    let numbers = value.split(',')
+        .map(|number| number.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("{}: {}", value, e))?;
+    match numbers[..] {
+        [x, y, z] => Ok((x, y, z)),
+        _ => Err(format!("{} isn't a x,y,z value", value)),
+    }
+}
+
+fn layer_gids(layer: &Node) -> Result<Vec<u32>, String> {
+    let data = layer.children().find(|node| node.has_tag_name("data"))
+        .ok_or_else(|| "a tile layer has no data".to_string())?;
+    if data.attribute("encoding") != Some("csv") {
+        return Err("only csv encoded tile layers are supported".to_string());
+    }
+    data.text().unwrap_or("")
+        .split(',')
+        .map(|gid| gid.trim())
+        .filter(|gid| !gid.is_empty())
+        .map(|gid| gid.parse::<u32>().map_err(|e| format!("tile {}: {}", gid, e)))
+        .collect()
+}
+
+//Turns a global tile id into the tile id of its tileset, gid 0 is an empty cell
+fn tile_id(gid: u32, first_gids: &[u32]) -> Option<i32> {
+    let gid = gid & !FLIP_FLAGS;
+    if gid == 0 { return None; }
+    first_gids.iter()
+        .filter(|first_gid| **first_gid <= gid)
+        .max()
+        .map(|first_gid| (gid - first_gid) as i32)
+}
+
+//The cell under the center of the object
+fn object_cell(object: &Node, y: i32, tile_width: f32, tile_length: f32) -> Result<GridCell, String> {
+    let width = float_attribute(object, "width")?;
+    let height = float_attribute(object, "height")?;
+    let center_x = float_attribute(object, "x")? + width / 2.0;
+    let mut center_z = float_attribute(object, "y")? + height / 2.0;
+    //Tile objects are placed by their bottom left corner
+    if object.attribute("gid").is_some() {
+        center_z -= height;
+    }
+    Ok(GridCell::new((center_x / tile_width).floor() as i32, y, (center_z / tile_length).floor() as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Two tilesets, a flipped tile, a rectangle and a tile object on the objects layer
+    //and a second objects layer a level higher
+    const MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.9" orientation="orthogonal" width="3" height="2" tilewidth="32" tileheight="32">
+ <properties>
+  <property name="chain" value="2"/>
+ </properties>
+ <tileset firstgid="1" source="blocks.tsx"/>
+ <tileset firstgid="5" source="more.tsx"/>
+ <layer id="1" name="layer0" width="3" height="2">
+  <data encoding="csv">
+1,1,1,
+1,1,4
+</data>
+ </layer>
+ <layer id="2" name="layer1" width="3" height="2">
+  <data encoding="csv">
+0,0,6,
+0,0,2147483650
+</data>
+ </layer>
+ <layer id="3" name="layer2" width="3" height="2">
+  <data encoding="csv">
+0,0,0,
+0,0,0
+</data>
+ </layer>
+ <objectgroup id="4" name="objects">
+  <object id="1" type="player" x="0" y="32" width="32" height="32"/>
+  <object id="2" class="block" gid="3" x="32" y="64" width="32" height="32">
+   <properties>
+    <property name="color" value="blue"/>
+    <property name="kind" value="heavy"/>
+   </properties>
+  </object>
+  <object id="3" type="goal" x="32" y="0" width="32" height="32">
+   <properties>
+    <property name="color" value="blue"/>
+   </properties>
+  </object>
+ </objectgroup>
+ <objectgroup id="5" name="up high">
+  <properties>
+   <property name="y" type="int" value="2"/>
+  </properties>
+  <object id="4" type="goal" x="70" y="40" width="16" height="16"/>
+ </objectgroup>
+</map>
+"#;
+
+    #[test]
+    fn tile_ids_count_from_the_first_gid_of_their_tileset() {
+        let level = parse_tmx(MAP, "map").unwrap();
+        assert_eq!((level.size.width, level.size.length, level.size.height), (3, 2, 3));
+        let tile = |x, y, z| level.grid.grid.get(&GridCell::new(x, y, z)).copied();
+        assert_eq!(tile(0, 0, 0), Some(Some(GridCellType::Tile(0))));
+        assert_eq!(tile(2, 0, 1), Some(Some(GridCellType::Tile(3))));
+        //Gid 6 is the second tile of the tileset starting at 5
+        assert_eq!(tile(2, 1, 0), Some(Some(GridCellType::Tile(1))));
+        //The flip flags are left out
+        assert_eq!(tile(2, 1, 1), Some(Some(GridCellType::Tile(1))));
+        assert_eq!(tile(0, 1, 0), Some(None));
+    }
+
+    #[test]
+    fn objects_go_on_the_cell_under_their_center() {
+        let level = parse_tmx(MAP, "map").unwrap();
+        assert_eq!(level.player, GridCell::new(0, 1, 1));
+        //The tile object's y is its bottom edge, it covers the row above it
+        assert_eq!(level.blocks, vec![Block { cell: GridCell::new(1, 1, 1), color: Some(BlockColor::Blue), kind: BlockKind::Heavy }]);
+        assert_eq!(level.goals, vec![
+            Goal { cell: GridCell::new(1, 1, 0), color: Some(BlockColor::Blue) },
+            Goal { cell: GridCell::new(2, 2, 1), color: None },
+        ]);
+        assert_eq!(level.rules.chain, 2);
+        assert_eq!(level.name, "map");
+    }
+
+    #[test]
+    fn maps_without_a_player_or_with_short_layers_are_refused() {
+        let no_player = MAP.replace(r#"type="player""#, r#"type="nothing""#);
+        assert_eq!(parse_tmx(&no_player, "map").err().unwrap(), "there is no player object");
+        let short = MAP.replace("0,0,6,", "0,0,");
+        assert!(parse_tmx(&short, "map").is_err());
+    }
+
+    #[test]
+    fn the_first_level_reads_like_its_map() {
+        let level = parse_tmx(include_str!("../assets/levels/level01.tmx"), "level01").unwrap();
+        assert_eq!(level.name, "Level One");
+        assert_eq!((level.size.width, level.size.length, level.size.height), (8, 9, 2));
+        assert_eq!(level.grid.grid.get(&GridCell::new(5, 1, 0)).copied(), Some(None));
+        assert_eq!(level.grid.grid.get(&GridCell::new(0, 1, 0)).copied(), Some(Some(GridCellType::Tile(1))));
+        assert_eq!(level.player, GridCell::new(6, 1, 4));
+        let blocks: Vec<GridCell> = level.blocks.iter().map(|block| block.cell).collect();
+        assert_eq!(blocks, vec![GridCell::new(2, 1, 2), GridCell::new(4, 1, 4), GridCell::new(2, 1, 6)]);
+        let goals: Vec<GridCell> = level.goals.iter().map(|goal| goal.cell).collect();
+        assert_eq!(goals, vec![GridCell::new(6, 1, 5), GridCell::new(6, 1, 6), GridCell::new(6, 1, 7)]);
+        assert_eq!(level.camera.position, (3.75, 10.0, 10.0));
+    }
+}
@@ -0,0 +1,43 @@
+use super::puzzle::Facing;
+
+//What a tile does in the puzzle. Floor tiles act on whatever stands on top of them,
+//so a tile at y 0 changes how the cell at y 1 plays.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum TileBehavior {
+    Floor,
+    Wall,
+    //The player and the blocks slide over it until something stops them or the ice ends
+    Ice,
+    //Moves whatever stands on it one cell at the end of every step
+    Conveyor(Facing),
+    //Can only be walked or pushed onto going this way
+    Gate(Facing),
+    //Opens the doors while a block rests on it, on every switch when there are more
+    Switch,
+    //Nothing gets onto it while it is closed
+    Door,
+}
+
+//Every tile id the game knows, the index of the tile in the Tiled tileset and in the csv layers
+pub const TILES: [(i32, TileBehavior, &str); 13] = [
+    (0, TileBehavior::Floor, "Floor"),
+    (1, TileBehavior::Wall, "Wall"),
+    (2, TileBehavior::Ice, "Ice"),
+    (3, TileBehavior::Conveyor(Facing::Up), "Conveyor up"),
+    (4, TileBehavior::Conveyor(Facing::Down), "Conveyor down"),
+    (5, TileBehavior::Conveyor(Facing::Right), "Conveyor right"),
+    (6, TileBehavior::Conveyor(Facing::Left), "Conveyor left"),
+    (7, TileBehavior::Gate(Facing::Up), "Gate up"),
+    (8, TileBehavior::Gate(Facing::Down), "Gate down"),
+    (9, TileBehavior::Gate(Facing::Right), "Gate right"),
+    (10, TileBehavior::Gate(Facing::Left), "Gate left"),
+    (11, TileBehavior::Switch, "Switch"),
+    (12, TileBehavior::Door, "Door"),
+];
+
+//Ids the game doesn't know are solid, like every tile used to be
+pub fn tile_behavior(id: i32) -> TileBehavior {
+    TILES.iter()
+        .find(|(tile, _, _)| *tile == id)
+        .map_or(TileBehavior::Wall, |(_, behavior, _)| *behavior)
+}
@@ -47,3 +47,36 @@ pub fn spawn_button(commands: &mut Commands, asset_server: &Res<AssetServer>, te
     }).id()
 }
 
+
+//A row along the top of the screen for the buttons and the text shown while playing
+pub fn spawn_hud_container(commands: &mut Commands) -> Entity{
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect{ top: Val::Px(0.), left: Val::Px(0.), ..default() },
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        }).id()
+}
+
+pub fn spawn_text(commands: &mut Commands, asset_server: &Res<AssetServer>, text: &str, comp: impl Component,) -> Entity{
+    commands.spawn_bundle(TextBundle::from_section(
+        text,
+        TextStyle {
+            font: asset_server.load("fonts/retganon.ttf"),
+            font_size: 30.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    ).with_style(Style {
+        margin: UiRect::all(Val::Px(12.)),
+        ..default()
+    }))
+    .insert(comp)
+    .id()
+}
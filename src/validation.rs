@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use super::game_grid::{GridCell, GridCellType};
+use super::goals::BLOCK_COLORS;
+use super::level_description::Level;
+use super::puzzle::PuzzleState;
+use super::deadlock::{dead_squares, distances_to_each_goal, goal_height};
+
+//Everything wrong with the spawns and the goals of a level, empty when the level can be played.
+//Whether it can be solved is left to the solver.
+pub fn check_level(level: &Level) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    check_spawn(level, "the player", level.player, &mut problems);
+    let blocks: Vec<GridCell> = level.blocks.iter().map(|block| block.cell).collect();
+    for block in blocks.iter() {
+        check_spawn(level, "a block", *block, &mut problems);
+    }
+    if blocks.contains(&level.player) {
+        problems.push(format!("the player and a block both spawn on {}", cell_name(level.player)));
+    }
+    for cell in duplicates(&blocks) {
+        problems.push(format!("two blocks spawn on {}", cell_name(cell)));
+    }
+
+    let goals: Vec<GridCell> = level.goals.iter().map(|goal| goal.cell).collect();
+    for goal in goals.iter() {
+        check_spawn(level, "a goal", *goal, &mut problems);
+    }
+    for cell in duplicates(&goals) {
+        problems.push(format!("the goal on {} is there twice", cell_name(cell)));
+    }
+    if level.rules.chain == 0 {
+        problems.push("the chain rule has to let the player push at least one block".to_string());
+    }
+
+    //Extra blocks are fine, they can fill holes
+    if level.goals.len() > level.blocks.len() {
+        problems.push(format!("{} goals for {} blocks", level.goals.len(), level.blocks.len()));
+    }
+    for color in BLOCK_COLORS {
+        let colored_goals = level.goals.iter().filter(|goal| goal.color == Some(color)).count();
+        let colored_blocks = level.blocks.iter().filter(|block| block.color == Some(color)).count();
+        if colored_goals > colored_blocks {
+            problems.push(format!("{} {} goals for {} {} blocks", colored_goals, color.name(), colored_blocks, color.name()));
+        }
+    }
+
+    //The pushes below only make sense once everything stands where it can
+    if problems.is_empty() {
+        check_pushes(level, &mut problems);
+    }
+    problems
+}
+
+//Spawns and goals go on an empty cell of the grid with a tile right under it
+fn check_spawn(level: &Level, what: &str, cell: GridCell, problems: &mut Vec<String>) {
+    match level.grid.grid.get(&cell) {
+        None => problems.push(format!("{} is outside the grid on {}", what, cell_name(cell))),
+        Some(Some(GridCellType::Tile(_))) => problems.push(format!("{} is inside a wall on {}", what, cell_name(cell))),
+        _ => {
+            let below = cell + GridCell::new(0, -1, 0);
+            if !matches!(level.grid.grid.get(&below), Some(Some(_))) {
+                problems.push(format!("{} has no ground under it on {}", what, cell_name(cell)));
+            }
+        },
+    }
+}
+
+//Goals no block can ever be pushed onto and blocks that can't be pushed onto any goal, more of them than the spare blocks
+fn check_pushes(level: &Level, problems: &mut Vec<String>) {
+    //Pulled and conveyed blocks reach goals pushes can't, and so do blocks pushed off a ledge, only the solver can tell
+    let puzzle = PuzzleState::from_level(level);
+    if level.rules.pull || !puzzle.conveyors.is_empty() { return; }
+    let height = goal_height(&puzzle);
+    if level.blocks.iter().any(|block| Some(block.cell.y) != height) { return; }
+    for (goal, distances) in level.goals.iter().zip(distances_to_each_goal(&puzzle)) {
+        if !level.blocks.iter().any(|block| goal.accepts(block.color) && distances.contains_key(&block.cell)) {
+            problems.push(format!("no block can be pushed onto the goal on {}", cell_name(goal.cell)));
+        }
+    }
+    //Only a problem when the blocks left over can't cover every goal
+    let dead = dead_squares(&puzzle);
+    let on_dead: Vec<&GridCell> = puzzle.blocks.iter().filter(|block| dead.contains(block)).collect();
+    if on_dead.len() > level.blocks.len() - level.goals.len() {
+        for block in on_dead {
+            problems.push(format!("the block on {} can't be pushed onto any goal", cell_name(*block)));
+        }
+    }
+}
+
+fn duplicates(cells: &[GridCell]) -> Vec<GridCell> {
+    let mut seen = HashSet::new();
+    cells.iter().filter(|cell| !seen.insert(**cell)).copied().collect()
+}
+
+pub fn cell_name(cell: GridCell) -> String {
+    format!("({}, {}, {})", cell.x, cell.y, cell.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::goals::{Block, Goal};
+    use super::super::xsb::parse_xsb;
+
+    fn level(text: &str) -> Level {
+        parse_xsb(text, "test").unwrap().remove(0)
+    }
+
+    const BOARD: &str = "\
+######
+#@$ .#
+######
+";
+
+    #[test]
+    fn a_playable_level_has_no_problems() {
+        assert!(check_level(&level(BOARD)).is_empty());
+    }
+
+    #[test]
+    fn spawns_outside_the_grid_are_found() {
+        let mut outside = level(BOARD);
+        outside.player = GridCell::new(-1, 1, 1);
+        outside.blocks[0].cell = GridCell::new(2, 1, 9);
+        assert_eq!(check_level(&outside), vec![
+            "the player is outside the grid on (-1, 1, 1)".to_string(),
+            "a block is outside the grid on (2, 1, 9)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn a_block_in_a_wall_is_found() {
+        let mut walled = level(BOARD);
+        walled.blocks[0].cell = GridCell::new(2, 1, 0);
+        assert_eq!(check_level(&walled), vec!["a block is inside a wall on (2, 1, 0)".to_string()]);
+    }
+
+    #[test]
+    fn more_goals_than_blocks_are_found() {
+        let mut short = level(BOARD);
+        short.goals.push(Goal::new(GridCell::new(3, 1, 1)));
+        assert_eq!(check_level(&short), vec!["2 goals for 1 blocks".to_string()]);
+        //A spare block is fine
+        let mut spare = level(BOARD);
+        spare.blocks.push(Block::new(GridCell::new(3, 1, 1)));
+        assert!(check_level(&spare).is_empty());
+    }
+
+    #[test]
+    fn a_block_that_cannot_reach_the_goal_is_found() {
+        let cornered = level("\
+######
+#$@ .#
+######
+");
+        assert_eq!(check_level(&cornered), vec![
+            "no block can be pushed onto the goal on (4, 1, 1)".to_string(),
+            "the block on (1, 1, 1) can't be pushed onto any goal".to_string(),
+        ]);
+    }
+}
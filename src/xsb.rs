@@ -0,0 +1,336 @@
+use std::{collections::HashSet, fs, path::Path};
+use super::game_grid::{GameGrid, GridCell, GridCellType};
+use super::goals::{Block, Goal};
+use super::level_description::{Level, LevelCamera, LevelError, LevelRules, LevelSize};
+use super::tiles::{tile_behavior, TileBehavior};
+
+//The tiles an imported level is built from, the same the game's own levels use
+const FLOOR_TILE: i32 = 0;
+const WALL_TILE: i32 = 1;
+//The floor is the bottom layer, walls, boxes, goals and the player stand on it
+const FLOOR_Y: i32 = 0;
+const OBJECT_Y: i32 = 1;
+
+//Every character a board line can have, '-' and '_' are floor like a space
+const BOARD_CHARS: &str = "#@+$*. -_";
+
+//Loads every level of a .xsb file, a collection is played in the order of the file
+pub fn load_xsb(path: &Path) -> Result<Vec<Level>, LevelError> {
+    let text = fs::read_to_string(path).map_err(|e| LevelError::Io(path.to_path_buf(), e))?;
+    let collection = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    parse_xsb(&text, &collection).map_err(|e| LevelError::Parse(path.to_path_buf(), e))
+}
+
+//The levels of a collection. A board is a block of lines made of the board characters.
+//A "Title:" line after a board names it, otherwise the comment or text line right before it does,
+//and levels without either are numbered after the collection.
+//A "Title:" line before the first board names the whole collection.
+pub fn parse_xsb(text: &str, collection: &str) -> Result<Vec<Level>, String> {
+    let mut collection = collection.to_string();
+    let mut levels: Vec<Level> = Vec::new();
+    let mut board: Vec<&str> = Vec::new();
+    let mut name_before: Option<String> = None;
+    //A board was just read and can still get its title
+    let mut titled = true;
+
+    //A blank line at the end closes the last board too
+    for line in text.lines().chain(std::iter::once("")) {
+        let line = line.trim_end();
+        if is_board_line(line) {
+            board.push(line);
+            continue;
+        }
+        if !board.is_empty() {
+            let number = levels.len() + 1;
+            let name = name_before.take().unwrap_or_else(|| format!("{} {}", collection, number));
+            let level = board_level(&board, name, &collection).map_err(|e| format!("level {}: {}", number, e))?;
+            levels.push(level);
+            board.clear();
+            titled = false;
+        }
+
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            if key.trim().eq_ignore_ascii_case("title") && !value.is_empty() {
+                match levels.last_mut() {
+                    Some(level) if !titled => level.name = value.to_string(),
+                    None => collection = value.to_string(),
+                    _ => {},
+                }
+                titled = true;
+            }
+            //Author, comment and the other fields aren't kept
+            continue;
+        }
+        let text = line.trim_start_matches(';').trim();
+        if !text.is_empty() {
+            name_before = Some(text.to_string());
+        }
+    }
+
+    if levels.is_empty() {
+        return Err("there is no level in the file".to_string());
+    }
+    Ok(levels)
+}
+
+fn is_board_line(line: &str) -> bool {
+    line.contains('#') && line.chars().all(|c| BOARD_CHARS.contains(c))
+}
+
+//Two layers: floor under everything the player can reach and under the walls, the rest on top of it
+fn board_level(rows: &[&str], name: String, collection: &str) -> Result<Level, String> {
+    let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0) as i32;
+    let length = rows.len() as i32;
+    let size = LevelSize { width, length, height: 2 };
+
+    let mut walls = HashSet::new();
+    let mut player = None;
+    let mut blocks = Vec::new();
+    let mut goals = Vec::new();
+    for (z, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let cell = GridCell::new(x as i32, OBJECT_Y, z as i32);
+            match c {
+                '#' => { walls.insert(cell); },
+                '@' | '+' => {
+                    if player.is_some() {
+                        return Err(format!("a second player on {},{}", x, z));
+                    }
+                    player = Some(cell);
+                },
+                '$' | '*' => blocks.push(Block::new(cell)),
+                _ => {},
+            }
+            if matches!(c, '.' | '+' | '*') {
+                goals.push(Goal::new(cell));
+            }
+        }
+    }
+    let player = player.ok_or_else(|| "there is no player".to_string())?;
+
+    let mut grid = GameGrid::new(size.width, size.length, size.height);
+    for cell in reachable(player, &walls, size).into_iter().chain(walls.iter().copied()) {
+        grid.grid.insert(GridCell::new(cell.x, FLOOR_Y, cell.z), Some(GridCellType::Tile(FLOOR_TILE)));
+    }
+    for wall in walls {
+        grid.grid.insert(wall, Some(GridCellType::Tile(WALL_TILE)));
+    }
+
+    Ok(Level {
+        name,
+        file: String::new(),
+        collection: Some(collection.to_string()),
+        size,
+        grid,
+        player,
+        blocks,
+        goals,
+        camera: LevelCamera::framing(size),
+        rules: LevelRules::default(),
+    })
+}
+
+//The cells inside the walls, found from the player without minding the boxes
+fn reachable(player: GridCell, walls: &HashSet<GridCell>, size: LevelSize) -> HashSet<GridCell> {
+    let mut found = HashSet::from([player]);
+    let mut open = vec![player];
+    while let Some(cell) = open.pop() {
+        for offset in [GridCell::new(1, 0, 0), GridCell::new(-1, 0, 0), GridCell::new(0, 0, 1), GridCell::new(0, 0, -1)] {
+            let next = cell + offset;
+            if next.x < 0 || next.z < 0 || next.x >= size.width || next.z >= size.length { continue; }
+            if walls.contains(&next) || !found.insert(next) { continue; }
+            open.push(next);
+        }
+    }
+    found
+}
+
+//The level as a .xsb board with its title under it. XSB has a single floor, so the level has to be
+//two high with everything standing on y 1, and there must be ground under every cell the player can reach.
+//Other sokoban tools only know the usual rules.
+pub fn to_xsb(level: &Level) -> Result<String, String> {
+    if level.size.height != 2 {
+        return Err(format!("the level is {} high, XSB only has a floor with walls on it", level.size.height));
+    }
+    if !level.rules.is_default() {
+        return Err("the level changes the rules, XSB boards are played with the usual ones".to_string());
+    }
+    let special = level.grid.grid.iter()
+        .filter_map(|(cell, tile)| match tile {
+            Some(GridCellType::Tile(id)) if !matches!(tile_behavior(*id), TileBehavior::Floor | TileBehavior::Wall) => Some((*cell, *id)),
+            _ => None,
+        })
+        .min_by_key(|(cell, _)| (cell.y, cell.z, cell.x));
+    if let Some((cell, id)) = special {
+        return Err(format!("the tile {} on {},{},{} does more than a floor or a wall, XSB only has those", id, cell.x, cell.y, cell.z));
+    }
+    let on_floor = |what: &str, cell: &GridCell| {
+        if cell.y == OBJECT_Y { Ok(()) } else { Err(format!("the {} on {},{},{} isn't on the floor", what, cell.x, cell.y, cell.z)) }
+    };
+    on_floor("player", &level.player)?;
+    for block in level.blocks.iter() { on_floor("block", &block.cell)?; }
+    for goal in level.goals.iter() { on_floor("goal", &goal.cell)?; }
+    let colored = level.blocks.iter().map(|block| (block.cell, block.color))
+        .chain(level.goals.iter().map(|goal| (goal.cell, goal.color)))
+        .find(|(_, color)| color.is_some());
+    if let Some((cell, _)) = colored {
+        return Err(format!("the block or goal on {},{},{} has a color, XSB boxes and goals have none", cell.x, cell.y, cell.z));
+    }
+    if let Some(block) = level.blocks.iter().find(|block| !block.kind.is_plain()) {
+        let cell = block.cell;
+        return Err(format!("the block on {},{},{} is {}, XSB boxes are all plain", cell.x, cell.y, cell.z, block.kind.name()));
+    }
+
+    let walls: HashSet<GridCell> = level.grid.grid.iter()
+        .filter(|(cell, tile)| cell.y == OBJECT_Y && matches!(tile, Some(GridCellType::Tile(_))))
+        .map(|(cell, _)| *cell)
+        .collect();
+    let mut holes: Vec<GridCell> = reachable(level.player, &walls, level.size).into_iter()
+        .filter(|cell| !matches!(level.grid.grid.get(&GridCell::new(cell.x, FLOOR_Y, cell.z)), Some(Some(GridCellType::Tile(_)))))
+        .collect();
+    if !holes.is_empty() {
+        holes.sort_by_key(|cell| (cell.z, cell.x));
+        let cells: Vec<String> = holes.iter().map(|cell| format!("{},{}", cell.x, cell.z)).collect();
+        return Err(format!("there is no floor under {}, XSB can't have holes", cells.join(" ")));
+    }
+
+    let mut text = String::new();
+    for z in 0..level.size.length {
+        let row: String = (0..level.size.width)
+            .map(|x| {
+                let cell = GridCell::new(x, OBJECT_Y, z);
+                let goal = level.goals.iter().any(|goal| goal.cell == cell);
+                if walls.contains(&cell) {
+                    '#'
+                } else if level.player == cell {
+                    if goal { '+' } else { '@' }
+                } else if level.blocks.iter().any(|block| block.cell == cell) {
+                    if goal { '*' } else { '$' }
+                } else if goal {
+                    '.'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        text.push_str(row.trim_end());
+        text.push('\n');
+    }
+    text.push_str(&format!("Title: {}\n", level.name));
+    Ok(text)
+}
+
+//The puzzle of a single board, the rule tests draw their levels this way
+#[cfg(test)]
+pub fn board(text: &str) -> super::puzzle::PuzzleState {
+    super::puzzle::PuzzleState::from_level(&parse_xsb(text, "test").unwrap()[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::level_description::LevelDescription;
+
+    const COLLECTION: &str = "\
+Title: Small Ones
+; The first one
+####
+#@$.#
+#####
+
+; A comment before the board
+#####
+#.$@#
+#####
+Title: Second
+Author: someone
+
+#####
+# *+#
+#####
+";
+
+    fn sorted(mut cells: Vec<GridCell>) -> Vec<GridCell> {
+        cells.sort_by_key(|cell| (cell.y, cell.z, cell.x));
+        cells
+    }
+
+    #[test]
+    fn reads_every_board_of_a_collection_with_its_name() {
+        let levels = parse_xsb(COLLECTION, "file").unwrap();
+        let names: Vec<&str> = levels.iter().map(|level| level.name.as_str()).collect();
+        assert_eq!(names, vec!["The first one", "Second", "Small Ones 3"]);
+        assert!(levels.iter().all(|level| level.collection.as_deref() == Some("Small Ones")));
+    }
+
+    #[test]
+    fn boards_are_a_floor_with_everything_on_it() {
+        let level = &parse_xsb(COLLECTION, "file").unwrap()[0];
+        assert_eq!((level.size.width, level.size.length, level.size.height), (5, 3, 2));
+        assert_eq!(level.player, GridCell::new(1, OBJECT_Y, 1));
+        assert_eq!(level.blocks, vec![Block::new(GridCell::new(2, OBJECT_Y, 1))]);
+        assert_eq!(level.goals, vec![Goal::new(GridCell::new(3, OBJECT_Y, 1))]);
+        assert_eq!(level.grid.grid[&GridCell::new(0, OBJECT_Y, 1)], Some(GridCellType::Tile(WALL_TILE)));
+        assert_eq!(level.grid.grid[&GridCell::new(2, FLOOR_Y, 1)], Some(GridCellType::Tile(FLOOR_TILE)));
+        //Nothing is under the outside of the walls
+        assert_eq!(level.grid.grid[&GridCell::new(4, FLOOR_Y, 0)], None);
+    }
+
+    #[test]
+    fn the_player_and_the_boxes_can_stand_on_goals() {
+        let level = &parse_xsb(COLLECTION, "file").unwrap()[2];
+        assert_eq!(level.player, GridCell::new(3, OBJECT_Y, 1));
+        assert_eq!(level.blocks, vec![Block::new(GridCell::new(2, OBJECT_Y, 1))]);
+        assert_eq!(sorted(level.goals.iter().map(|goal| goal.cell).collect()),
+            vec![GridCell::new(2, OBJECT_Y, 1), GridCell::new(3, OBJECT_Y, 1)]);
+    }
+
+    #[test]
+    fn a_board_needs_exactly_one_player() {
+        let none = parse_xsb("#####\n# $.#\n#####\n", "file").err().unwrap();
+        assert!(none.contains("there is no player"), "{}", none);
+        let two = parse_xsb("######\n#@$.@#\n######\n", "file").err().unwrap();
+        assert!(two.contains("a second player"), "{}", two);
+        assert!(parse_xsb("; only a comment\n", "file").is_err());
+    }
+
+    #[test]
+    fn exported_boards_read_back_the_same() {
+        for level in parse_xsb(COLLECTION, "file").unwrap() {
+            let text = to_xsb(&level).unwrap();
+            let again = parse_xsb(&text, "file").unwrap().remove(0);
+            assert_eq!(again.name, level.name);
+            assert_eq!(again.player, level.player);
+            assert_eq!(again.blocks, level.blocks);
+            assert_eq!(sorted(again.goals.iter().map(|goal| goal.cell).collect()),
+                sorted(level.goals.iter().map(|goal| goal.cell).collect()));
+            assert!(again.grid.grid == level.grid.grid);
+        }
+    }
+
+    #[test]
+    fn imported_boards_export_to_ron_and_read_back_the_same() {
+        for level in parse_xsb(COLLECTION, "file").unwrap() {
+            let (description, layers) = level.to_description("test");
+            let again: LevelDescription = ron::from_str(&description.to_ron().unwrap()).unwrap();
+            assert_eq!(again.name, level.name);
+            assert_eq!(again.player, level.player);
+            assert_eq!(again.blocks, level.blocks);
+            assert_eq!(again.goals, level.goals);
+            assert_eq!(again.layers, vec!["maps/test0.csv".to_string(), "maps/test1.csv".to_string()]);
+            let grid = GameGrid::new(level.size.width, level.size.length, level.size.height)
+                .tiles_from_csv(layers.into_iter().map(|(_, csv)| csv).collect());
+            assert!(grid.grid == level.grid.grid);
+        }
+    }
+
+    #[test]
+    fn export_leaves_out_what_xsb_cant_hold() {
+        let mut level = parse_xsb(COLLECTION, "file").unwrap().remove(0);
+        level.rules.pull = true;
+        assert!(to_xsb(&level).is_err());
+    }
+}